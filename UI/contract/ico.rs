@@ -1,4 +1,8 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar::instructions::{
+    load_current_index_checked, load_instruction_at_checked,
+};
+use anchor_lang::solana_program::{ed25519_program, keccak};
 use anchor_spl::token::{self, Mint, TokenAccount};
 
 declare_id!("4VQtJnM2uUaeVXUUncE98ucVuyXGnPEbtqMjdmJrhyK8");
@@ -10,443 +14,4046 @@ pub mod advanced_ico_program {
     pub fn initialize(
         ctx: Context<Initialize>,
         total_supply: u64,
-        seed_price: u64,
-        pre_ico_price: u64,
-        public_price: u64,
-        start_time: i64,
-        duration: i64,
+        rounds: Vec<RoundConfig>,
+        soft_cap: u64,
     ) -> Result<()> {
+        let rounds = rounds
+            .iter()
+            .map(|config| config.resolve(total_supply))
+            .collect::<Result<Vec<_>>>()?;
+        RoundSchedule::validate_schedule(&rounds)?;
+
+        // Fund the escrow with its rent-exempt minimum so small first purchases can land in it
+        let escrow_rent = Rent::get()?.minimum_balance(0);
+        let transfer_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+                from: ctx.accounts.authority.to_account_info(),
+                to: ctx.accounts.escrow.to_account_info(),
+            },
+        );
+        anchor_lang::system_program::transfer(transfer_context, escrow_rent)?;
+
         let ico = &mut ctx.accounts.ico_account;
         ico.authority = ctx.accounts.authority.key();
+        ico.pending_authority = Pubkey::default();
+        // Roles start unassigned; the authority can act in every role itself
+        ico.pauser = Pubkey::default();
+        ico.whitelist_manager = Pubkey::default();
+        ico.round_operator = Pubkey::default();
+        ico.distributor = Pubkey::default();
+        ico.treasurer = Pubkey::default();
         ico.token_mint = ctx.accounts.token_mint.key();
+        ico.token_vault = ctx.accounts.token_vault.key();
+        ico.token_decimals = ctx.accounts.token_mint.decimals;
+        ico.treasury_wallet = ctx.accounts.treasury_wallet.key();
         ico.total_supply = total_supply;
-        ico.seed_price = seed_price;
-        ico.pre_ico_price = pre_ico_price;
-        ico.public_price = public_price;
-        ico.current_public_price = pre_ico_price; // Initialize with pre-ICO price
-        ico.start_time = start_time;
-        ico.duration = duration;
+        // The sale window spans the whole round schedule
+        ico.rounds = rounds;
+        ico.sync_sale_window()?;
         ico.tokens_sold = 0;
-        ico.seed_tokens_sold = 0;
-        ico.public_tokens_sold = 0;
+        ico.tokens_distributed = 0;
         ico.is_active = true;
-        ico.round_type = RoundType::PreICO;
-        ico.seed_investors = Vec::new();
+        ico.is_paused = false;
+        ico.soft_cap = soft_cap;
+        ico.total_raised = 0;
+        ico.usd_raised = 0;
+        ico.is_refunding = false;
+        ico.seed_merkle_root = [0u8; 32];
         ico.total_investors = 0;
         ico.purchase_counter = 0;
-        // Calculate allocations using integer arithmetic
-        // 0.5% = 5/1000 of total supply
-        ico.seed_round_allocation = total_supply
-            .checked_mul(5)
-            .ok_or(IcoError::MathOverflow)?
-            .checked_div(1000)
-            .ok_or(IcoError::MathOverflow)?;
-
-        // 1.5% = 15/1000 of total supply
-        ico.pre_ico_allocation = total_supply
-            .checked_mul(15)
-            .ok_or(IcoError::MathOverflow)?
-            .checked_div(1000)
-            .ok_or(IcoError::MathOverflow)?;
-
-        // 1.11% ≈ 111/10000 of total supply
-        ico.public_round_allocation = total_supply
-            .checked_mul(111)
-            .ok_or(IcoError::MathOverflow)?
-            .checked_div(10000)
-            .ok_or(IcoError::MathOverflow)?;
+        // Every round unlocks in full until a vesting schedule is configured
+        ico.seed_vesting = VestingSchedule::default();
+        ico.private_vesting = VestingSchedule::default();
+        ico.pre_ico_vesting = VestingSchedule::default();
+        ico.public_vesting = VestingSchedule::default();
+        // No per-wallet limits until they are configured
+        ico.seed_limits = PurchaseLimits::default();
+        ico.private_limits = PurchaseLimits::default();
+        ico.pre_ico_limits = PurchaseLimits::default();
+        ico.public_limits = PurchaseLimits::default();
+        ico.payment_mints = Vec::new();
+        // SOL purchases stay closed until a price oracle is configured
+        ico.price_oracle = Pubkey::default();
+        ico.oracle_program = Pubkey::default();
+        ico.max_price_staleness = 0;
+        ico.max_confidence_bps = 0;
+        // Purchases are open to any wallet until a KYC signer is configured
+        ico.kyc_signer = Pubkey::default();
+        ico.kyc_tier_wallet_bps = [10000; KYC_TIER_COUNT];
+        // Sensitive changes need council proposals only once a council is configured
+        ico.council = Pubkey::default();
+        // Allocations, whether given as bps or amounts, must fit within the total supply
+        require!(
+            ico.allocated_supply()? <= total_supply,
+            IcoError::AllocationExceedsSupply
+        );
 
         Ok(())
     }
 
-    pub fn add_seed_investor(ctx: Context<AddSeedInvestor>, investor: Pubkey) -> Result<()> {
+    pub fn update_ico_parameters(
+        ctx: Context<UpdateIcoParameters>,
+        params: IcoParametersUpdate,
+    ) -> Result<()> {
         let ico = &mut ctx.accounts.ico_account;
         require!(
-            ctx.accounts.authority.key() == ico.authority,
-            IcoError::Unauthorized
+            ico.council == Pubkey::default(),
+            IcoError::CouncilApprovalRequired
         );
+
+        apply_ico_parameters(ico, &params, Clock::get()?.unix_timestamp)
+    }
+
+    pub fn propose_authority(ctx: Context<ProposeAuthority>, new_authority: Pubkey) -> Result<()> {
         require!(
-            !ico.seed_investors.contains(&investor),
-            IcoError::InvestorAlreadyExists
+            new_authority != Pubkey::default(),
+            IcoError::InvalidPendingAuthority
         );
 
-        ico.seed_investors.push(investor);
+        let ico = &mut ctx.accounts.ico_account;
+        ico.pending_authority = new_authority;
+
+        emit!(AuthorityProposedEvent {
+            authority: ico.authority,
+            pending_authority: new_authority,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
         Ok(())
     }
 
-    pub fn remove_seed_investor(ctx: Context<RemoveSeedInvestor>, investor: Pubkey) -> Result<()> {
+    pub fn cancel_authority_transfer(ctx: Context<ProposeAuthority>) -> Result<()> {
         let ico = &mut ctx.accounts.ico_account;
         require!(
-            ctx.accounts.authority.key() == ico.authority,
-            IcoError::Unauthorized
+            ico.pending_authority != Pubkey::default(),
+            IcoError::InvalidPendingAuthority
         );
-        if let Some(index) = ico.seed_investors.iter().position(|&x| x == investor) {
-            ico.seed_investors.remove(index);
-        }
+        let cancelled_authority = ico.pending_authority;
+        ico.pending_authority = Pubkey::default();
+
+        emit!(AuthorityTransferCancelledEvent {
+            authority: ico.authority,
+            cancelled_authority,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
         Ok(())
     }
 
-    pub fn update_round(ctx: Context<UpdateRound>, new_round: RoundType) -> Result<()> {
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
         let ico = &mut ctx.accounts.ico_account;
+        let previous_authority = ico.authority;
+        ico.authority = ctx.accounts.pending_authority.key();
+        ico.pending_authority = Pubkey::default();
+
+        emit!(AuthorityTransferredEvent {
+            previous_authority,
+            new_authority: ico.authority,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn grant_role(ctx: Context<ManageRole>, role: Role, account: Pubkey) -> Result<()> {
         require!(
-            ctx.accounts.authority.key() == ico.authority,
-            IcoError::Unauthorized
+            ctx.accounts.ico_account.council == Pubkey::default(),
+            IcoError::CouncilApprovalRequired
         );
 
-        match new_round {
-            RoundType::PreICO => {
-                ico.round_type = RoundType::PreICO;
-                ico.current_public_price = ico.pre_ico_price;
-            }
-            RoundType::PublicICO => {
-                ico.round_type = RoundType::PublicICO;
-                ico.current_public_price = ico.public_price;
-            }
-        }
+        apply_role_grant(
+            &mut ctx.accounts.ico_account,
+            role,
+            account,
+            Clock::get()?.unix_timestamp,
+        )
+    }
+
+    pub fn revoke_role(ctx: Context<ManageRole>, role: Role) -> Result<()> {
+        require!(
+            ctx.accounts.ico_account.council == Pubkey::default(),
+            IcoError::CouncilApprovalRequired
+        );
 
-        emit!(RoundUpdateEvent {
-            round: new_round,
+        apply_role_revoke(
+            &mut ctx.accounts.ico_account,
+            role,
+            Clock::get()?.unix_timestamp,
+        )
+    }
+
+    pub fn configure_council(
+        ctx: Context<ConfigureCouncil>,
+        members: Vec<Pubkey>,
+        threshold: u8,
+        timelock: i64,
+    ) -> Result<()> {
+        Council::validate_config(&members, threshold, timelock)?;
+
+        let council = &mut ctx.accounts.council;
+        council.ico = ctx.accounts.ico_account.key();
+        council.members = members;
+        council.threshold = threshold;
+        council.timelock = timelock;
+        council.proposal_count = 0;
+        council.bump = ctx.bumps.council;
+
+        ctx.accounts.ico_account.council = council.key();
+
+        emit!(CouncilConfiguredEvent {
+            council: council.key(),
+            members: council.members.clone(),
+            threshold,
+            timelock,
             timestamp: Clock::get()?.unix_timestamp,
         });
 
         Ok(())
     }
 
-    pub fn buy_tokens(ctx: Context<BuyTokens>, amount: u64) -> Result<()> {
+    pub fn create_proposal(ctx: Context<CreateProposal>, action: ProposalAction) -> Result<()> {
         let current_time = Clock::get()?.unix_timestamp;
-        let ico = &mut ctx.accounts.ico_account;
-
-        require!(
-            ico.is_active
-                && current_time >= ico.start_time
-                && current_time < ico.start_time + ico.duration,
-            IcoError::IcoNotActive
-        );
-
-        let (price, allocation_limit) = if ico.seed_investors.contains(&ctx.accounts.buyer.key()) {
-            (
-                ico.seed_price,
-                ico.seed_round_allocation
-                    .checked_sub(ico.seed_tokens_sold)
-                    .ok_or(IcoError::MathOverflow)?,
-            )
+        let council = &mut ctx.accounts.council;
+        let proposer = ctx.accounts.proposer.key();
+        require!(council.is_member(&proposer), IcoError::NotCouncilMember);
+
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.council = council.key();
+        proposal.index = council.proposal_count;
+        proposal.proposer = proposer;
+        proposal.action = action;
+        // Proposing counts as the proposer's approval
+        proposal.approvals = vec![proposer];
+        proposal.created_at = current_time;
+        proposal.approved_at = if council.threshold <= 1 {
+            current_time
         } else {
-            let current_public_allocation = match ico.round_type {
-                RoundType::PreICO => ico.pre_ico_allocation,
-                RoundType::PublicICO => ico.public_round_allocation,
-            };
-            (
-                ico.current_public_price,
-                current_public_allocation
-                    .checked_sub(ico.public_tokens_sold)
-                    .ok_or(IcoError::MathOverflow)?,
-            )
+            0
         };
+        proposal.executed = false;
+        proposal.bump = ctx.bumps.proposal;
+
+        council.proposal_count = council
+            .proposal_count
+            .checked_add(1)
+            .ok_or(IcoError::MathOverflow)?;
 
+        emit!(ProposalCreatedEvent {
+            proposal: proposal.key(),
+            index: proposal.index,
+            proposer,
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+
+    pub fn approve_proposal(ctx: Context<ApproveProposal>) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let council = &ctx.accounts.council;
+        let member = ctx.accounts.member.key();
+        require!(council.is_member(&member), IcoError::NotCouncilMember);
+
+        let proposal = &mut ctx.accounts.proposal;
+        require!(!proposal.executed, IcoError::ProposalAlreadyExecuted);
         require!(
-            amount <= allocation_limit,
-            IcoError::RoundAllocationExceeded
+            !proposal.approvals.contains(&member),
+            IcoError::ProposalAlreadyApproved
         );
+        let approvals = proposal.add_approval(council, member, current_time);
 
-        let total_cost = amount.checked_mul(price).ok_or(IcoError::MathOverflow)?;
+        emit!(ProposalApprovedEvent {
+            proposal: proposal.key(),
+            member,
+            approvals,
+            timestamp: current_time,
+        });
 
-        let transfer_context = CpiContext::new(
-            ctx.accounts.system_program.to_account_info(),
-            anchor_lang::system_program::Transfer {
-                from: ctx.accounts.buyer.to_account_info(),
-                to: ctx.accounts.treasury_wallet.to_account_info(),
-            },
-        );
-        anchor_lang::system_program::transfer(transfer_context, total_cost)?;
+        Ok(())
+    }
 
-        let purchase = &mut ctx.accounts.purchase_account;
-        purchase.buyer = ctx.accounts.buyer.key();
-        purchase.amount = amount;
-        purchase.is_distributed = false;
-        purchase.timestamp = current_time;
-        purchase.ico = ico.key();
-        purchase.purchase_price = price;
-        purchase.round = ico.round_type;
-
-        if ico.seed_investors.contains(&ctx.accounts.buyer.key()) {
-            ico.seed_tokens_sold = ico
-                .seed_tokens_sold
-                .checked_add(amount)
-                .ok_or(IcoError::MathOverflow)?;
-        } else {
-            ico.public_tokens_sold = ico
-                .public_tokens_sold
-                .checked_add(amount)
-                .ok_or(IcoError::MathOverflow)?;
-        }
+    pub fn execute_proposal(ctx: Context<ExecuteProposal>) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let council = &mut ctx.accounts.council;
+        let proposal = &mut ctx.accounts.proposal;
+        require!(!proposal.executed, IcoError::ProposalAlreadyExecuted);
 
-        ico.tokens_sold = ico
-            .tokens_sold
-            .checked_add(amount)
+        // Approvals from members removed since approving no longer count
+        require!(
+            proposal.approved_at != 0
+                && council.count_approvals(&proposal.approvals) >= council.threshold,
+            IcoError::ThresholdNotReached
+        );
+        let executable_at = proposal
+            .approved_at
+            .checked_add(council.timelock)
             .ok_or(IcoError::MathOverflow)?;
+        require!(current_time >= executable_at, IcoError::TimelockNotElapsed);
 
-        ico.purchase_counter = ico
-            .purchase_counter
-            .checked_add(1)
-            .ok_or(IcoError::MathOverflow)?;
+        let ico = &mut ctx.accounts.ico_account;
+        match proposal.action.clone() {
+            ProposalAction::UpdateIcoParameters(params) => {
+                apply_ico_parameters(ico, &params, current_time)?;
+            }
+            ProposalAction::UpdateRound {
+                round,
+                start_time,
+                end_time,
+            } => {
+                apply_round(ico, round, start_time, end_time, current_time)?;
+            }
+            ProposalAction::SetTreasuryWallet(treasury_wallet) => {
+                require!(
+                    treasury_wallet != Pubkey::default(),
+                    IcoError::InvalidTreasuryWallet
+                );
+                emit!(TreasuryWalletUpdatedEvent {
+                    previous_wallet: ico.treasury_wallet,
+                    new_wallet: treasury_wallet,
+                    timestamp: current_time,
+                });
+                ico.treasury_wallet = treasury_wallet;
+            }
+            ProposalAction::GrantRole { role, account } => {
+                apply_role_grant(ico, role, account, current_time)?;
+            }
+            ProposalAction::RevokeRole(role) => {
+                apply_role_revoke(ico, role, current_time)?;
+            }
+            ProposalAction::ConfigureVesting(vesting) => {
+                apply_vesting(ico, vesting, current_time)?;
+            }
+            ProposalAction::ConfigurePurchaseLimits(limits) => {
+                apply_purchase_limits(ico, limits, current_time)?;
+            }
+            ProposalAction::ConfigureKyc {
+                kyc_signer,
+                tier_wallet_bps,
+            } => {
+                apply_kyc(ico, kyc_signer, tier_wallet_bps, current_time)?;
+            }
+            ProposalAction::UpdateCouncil {
+                members,
+                threshold,
+                timelock,
+            } => {
+                Council::validate_config(&members, threshold, timelock)?;
+                council.members = members;
+                council.threshold = threshold;
+                council.timelock = timelock;
+
+                emit!(CouncilConfiguredEvent {
+                    council: council.key(),
+                    members: council.members.clone(),
+                    threshold,
+                    timelock,
+                    timestamp: current_time,
+                });
+            }
+            ProposalAction::ConfigureSeedWhitelist(merkle_root) => {
+                apply_seed_whitelist(ico, merkle_root, current_time)?;
+            }
+            ProposalAction::AddSeedInvestor {
+                investor,
+                allocation,
+                price,
+            } => {
+                let seed_allocation = ctx
+                    .accounts
+                    .seed_allocation
+                    .as_mut()
+                    .ok_or(IcoError::MissingProposalAccount)?;
+                let bump = ctx
+                    .bumps
+                    .seed_allocation
+                    .ok_or(IcoError::MissingProposalAccount)?;
+                apply_seed_investor(
+                    ico,
+                    seed_allocation,
+                    bump,
+                    investor,
+                    allocation,
+                    price,
+                    current_time,
+                )?;
+            }
+            ProposalAction::AddPaymentMint { mint, prices } => {
+                let payment_mint = ctx
+                    .accounts
+                    .payment_mint
+                    .as_ref()
+                    .ok_or(IcoError::MissingProposalAccount)?;
+                require_keys_eq!(payment_mint.key(), mint, IcoError::InvalidPaymentMint);
+                let payment_vault = ctx
+                    .accounts
+                    .payment_vault
+                    .as_ref()
+                    .ok_or(IcoError::MissingProposalAccount)?;
+                apply_payment_mint(ico, mint, payment_vault.key(), prices)?;
+            }
+            ProposalAction::ConfigurePriceOracle {
+                price_oracle,
+                oracle_program,
+                max_price_staleness,
+                max_confidence_bps,
+            } => {
+                let feed = ctx
+                    .accounts
+                    .price_oracle
+                    .as_ref()
+                    .ok_or(IcoError::MissingProposalAccount)?;
+                require_keys_eq!(feed.key(), price_oracle, IcoError::InvalidOracle);
+                apply_price_oracle(
+                    ico,
+                    feed,
+                    oracle_program,
+                    max_price_staleness,
+                    max_confidence_bps,
+                )?;
+            }
+        }
 
-        ico.total_investors = ico
-            .total_investors
-            .checked_add(1)
-            .ok_or(IcoError::MathOverflow)?;
+        proposal.executed = true;
 
-        emit!(TokenPurchaseEvent {
-            buyer: ctx.accounts.buyer.key(),
-            amount,
-            price,
-            is_seed_round: ico.seed_investors.contains(&ctx.accounts.buyer.key()),
-            round: ico.round_type,
+        emit!(ProposalExecutedEvent {
+            proposal: proposal.key(),
+            executor: ctx.accounts.executor.key(),
             timestamp: current_time,
         });
 
         Ok(())
     }
 
-    pub fn distribute_tokens(ctx: Context<DistributeTokens>) -> Result<()> {
-        let purchase = &mut ctx.accounts.purchase_account;
+    pub fn pause(ctx: Context<SetPaused>) -> Result<()> {
+        let ico = &mut ctx.accounts.ico_account;
+        require!(!ico.is_paused, IcoError::SalePaused);
+        ico.is_paused = true;
 
-        require!(!purchase.is_distributed, IcoError::AlreadyDistributed);
+        emit!(PauseEvent {
+            paused: true,
+            by: ctx.accounts.pauser.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn unpause(ctx: Context<SetPaused>) -> Result<()> {
+        let ico = &mut ctx.accounts.ico_account;
+        require!(ico.is_paused, IcoError::SaleNotPaused);
+        ico.is_paused = false;
+
+        emit!(PauseEvent {
+            paused: false,
+            by: ctx.accounts.pauser.key(),
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
 
+    pub fn deposit_sale_tokens(ctx: Context<DepositSaleTokens>, amount: u64) -> Result<()> {
         let transfer_ctx = CpiContext::new(
             ctx.accounts.token_program.to_account_info(),
             token::Transfer {
-                from: ctx.accounts.treasury_token_account.to_account_info(),
-                to: ctx.accounts.buyer_token_account.to_account_info(),
-                authority: ctx.accounts.authority.to_account_info(),
+                from: ctx.accounts.treasurer_token_account.to_account_info(),
+                to: ctx.accounts.token_vault.to_account_info(),
+                authority: ctx.accounts.treasurer.to_account_info(),
             },
         );
 
-        token::transfer(transfer_ctx, purchase.amount)?;
+        token::transfer(transfer_ctx, amount)?;
 
-        purchase.is_distributed = true;
+        ctx.accounts.token_vault.reload()?;
 
-        emit!(TokenDistributionEvent {
-            buyer: purchase.buyer,
-            amount: purchase.amount,
-            round: purchase.round,
+        emit!(SaleTokensDepositedEvent {
+            depositor: ctx.accounts.treasurer.key(),
+            amount,
+            vault_balance: ctx.accounts.token_vault.amount,
         });
 
         Ok(())
     }
 
-    pub fn end_ico(ctx: Context<EndIco>) -> Result<()> {
-        let ico = &mut ctx.accounts.ico_account;
-        let current_time = Clock::get()?.unix_timestamp;
+    pub fn withdraw_sale_token_surplus(
+        ctx: Context<WithdrawSaleTokenSurplus>,
+        amount: u64,
+    ) -> Result<()> {
+        let reserved = ctx.accounts.ico_account.reserved_sale_tokens()?;
+        let surplus = ctx.accounts.token_vault.amount.saturating_sub(reserved);
         require!(
-            current_time >= ico.start_time + ico.duration,
-            IcoError::IcoStillActive
+            amount > 0 && amount <= surplus,
+            IcoError::InsufficientVaultBalance
         );
-        ico.is_active = false;
+
+        transfer_from_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.token_vault,
+            ctx.accounts.treasury_token_account.to_account_info(),
+            &ctx.accounts.ico_account,
+            ctx.bumps.ico_account,
+            amount,
+        )?;
+
+        ctx.accounts.token_vault.reload()?;
+
+        emit!(SaleTokensWithdrawnEvent {
+            destination: ctx.accounts.treasury_token_account.key(),
+            amount,
+            vault_balance: ctx.accounts.token_vault.amount,
+        });
+
         Ok(())
     }
-}
 
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Copy)]
-pub enum RoundType {
-    PreICO,
-    PublicICO,
-}
+    pub fn add_seed_investor(
+        ctx: Context<AddSeedInvestor>,
+        investor: Pubkey,
+        allocation: u64,
+        price: u64,
+    ) -> Result<()> {
+        let ico = &ctx.accounts.ico_account;
+        require!(
+            ico.has_role(
+                Role::WhitelistManager,
+                &ctx.accounts.whitelist_manager.key()
+            ),
+            IcoError::Unauthorized
+        );
+        require!(
+            ico.council == Pubkey::default(),
+            IcoError::CouncilApprovalRequired
+        );
 
-#[event]
-pub struct TokenPurchaseEvent {
-    pub buyer: Pubkey,
-    pub amount: u64,
-    pub price: u64,
-    pub is_seed_round: bool,
-    pub round: RoundType,
-    pub timestamp: i64,
-}
+        apply_seed_investor(
+            ico,
+            &mut ctx.accounts.seed_allocation,
+            ctx.bumps.seed_allocation,
+            investor,
+            allocation,
+            price,
+            Clock::get()?.unix_timestamp,
+        )
+    }
 
-#[event]
-pub struct TokenDistributionEvent {
-    pub buyer: Pubkey,
-    pub amount: u64,
-    pub round: RoundType,
-}
+    pub fn remove_seed_investor(ctx: Context<RemoveSeedInvestor>) -> Result<()> {
+        require!(
+            ctx.accounts.ico_account.has_role(
+                Role::WhitelistManager,
+                &ctx.accounts.whitelist_manager.key()
+            ),
+            IcoError::Unauthorized
+        );
 
-#[event]
-pub struct RoundUpdateEvent {
-    pub round: RoundType,
-    pub timestamp: i64,
-}
+        // Closing the allocation only blocks further seed buys; existing purchases stand
+        let seed_allocation = &ctx.accounts.seed_allocation;
+        emit!(SeedInvestorRemovedEvent {
+            investor: seed_allocation.investor,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
 
-#[account]
-#[derive(Default)]
-pub struct IcoAccount {
-    pub authority: Pubkey,            // 32
-    pub token_mint: Pubkey,           // 32
-    pub total_supply: u64,            // 8
-    pub seed_price: u64,              // 8
-    pub pre_ico_price: u64,           // 8
-    pub public_price: u64,            // 8
-    pub current_public_price: u64,    // 8
-    pub tokens_sold: u64,             // 8
-    pub seed_tokens_sold: u64,        // 8
-    pub public_tokens_sold: u64,      // 8
-    pub start_time: i64,              // 8
-    pub duration: i64,                // 8
-    pub is_active: bool,              // 1
-    pub round_type: RoundType,        // 1
-    pub seed_investors: Vec<Pubkey>,  // 4 + (32 * n)
-    pub total_investors: u64,         // 8
-    pub purchase_counter: u64,        // 8
-    pub seed_round_allocation: u64,   // 8
-    pub pre_ico_allocation: u64,      // 8
-    pub public_round_allocation: u64, // 8
-}
+        Ok(())
+    }
+
+    pub fn configure_seed_whitelist(
+        ctx: Context<ConfigureSeedWhitelist>,
+        merkle_root: [u8; 32],
+    ) -> Result<()> {
+        let ico = &mut ctx.accounts.ico_account;
+        require!(
+            ico.council == Pubkey::default(),
+            IcoError::CouncilApprovalRequired
+        );
+
+        apply_seed_whitelist(ico, merkle_root, Clock::get()?.unix_timestamp)
+    }
+
+    pub fn add_payment_mint(
+        ctx: Context<AddPaymentMint>,
+        seed_price: u64,
+        private_price: u64,
+        pre_ico_price: u64,
+        public_price: u64,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.ico_account.council == Pubkey::default(),
+            IcoError::CouncilApprovalRequired
+        );
+
+        apply_payment_mint(
+            &mut ctx.accounts.ico_account,
+            ctx.accounts.payment_mint.key(),
+            ctx.accounts.payment_vault.key(),
+            [seed_price, private_price, pre_ico_price, public_price],
+        )
+    }
+
+    pub fn configure_price_oracle(
+        ctx: Context<ConfigurePriceOracle>,
+        oracle_program: Pubkey,
+        max_price_staleness: i64,
+        max_confidence_bps: u16,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.ico_account.council == Pubkey::default(),
+            IcoError::CouncilApprovalRequired
+        );
+
+        apply_price_oracle(
+            &mut ctx.accounts.ico_account,
+            &ctx.accounts.price_oracle,
+            oracle_program,
+            max_price_staleness,
+            max_confidence_bps,
+        )
+    }
+
+    pub fn configure_vesting(
+        ctx: Context<ConfigureVesting>,
+        seed_vesting: VestingSchedule,
+        private_vesting: VestingSchedule,
+        pre_ico_vesting: VestingSchedule,
+        public_vesting: VestingSchedule,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.ico_account.council == Pubkey::default(),
+            IcoError::CouncilApprovalRequired
+        );
+
+        apply_vesting(
+            &mut ctx.accounts.ico_account,
+            [
+                seed_vesting,
+                private_vesting,
+                pre_ico_vesting,
+                public_vesting,
+            ],
+            Clock::get()?.unix_timestamp,
+        )
+    }
+
+    pub fn configure_purchase_limits(
+        ctx: Context<ConfigurePurchaseLimits>,
+        seed_limits: PurchaseLimits,
+        private_limits: PurchaseLimits,
+        pre_ico_limits: PurchaseLimits,
+        public_limits: PurchaseLimits,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.ico_account.council == Pubkey::default(),
+            IcoError::CouncilApprovalRequired
+        );
+
+        apply_purchase_limits(
+            &mut ctx.accounts.ico_account,
+            [seed_limits, private_limits, pre_ico_limits, public_limits],
+            Clock::get()?.unix_timestamp,
+        )
+    }
+
+    pub fn configure_kyc(
+        ctx: Context<ConfigureKyc>,
+        kyc_signer: Pubkey,
+        tier_wallet_bps: [u16; KYC_TIER_COUNT],
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.ico_account.council == Pubkey::default(),
+            IcoError::CouncilApprovalRequired
+        );
+
+        apply_kyc(
+            &mut ctx.accounts.ico_account,
+            kyc_signer,
+            tier_wallet_bps,
+            Clock::get()?.unix_timestamp,
+        )
+    }
+
+    pub fn update_round(
+        ctx: Context<UpdateRound>,
+        round: RoundType,
+        start_time: i64,
+        end_time: i64,
+    ) -> Result<()> {
+        let ico = &mut ctx.accounts.ico_account;
+        require!(
+            ico.has_role(Role::RoundOperator, &ctx.accounts.round_operator.key()),
+            IcoError::Unauthorized
+        );
+        require!(
+            ico.council == Pubkey::default(),
+            IcoError::CouncilApprovalRequired
+        );
+
+        apply_round(
+            ico,
+            round,
+            start_time,
+            end_time,
+            Clock::get()?.unix_timestamp,
+        )
+    }
+
+    pub fn buy_tokens(
+        ctx: Context<BuyTokens>,
+        amount: u64,
+        seed_proof: Option<SeedWhitelistProof>,
+    ) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let ico_key = ctx.accounts.ico_account.key();
+        let buyer_key = ctx.accounts.buyer.key();
+        let kyc_tier = KycAttestation::verify(
+            &ctx.accounts.ico_account,
+            &ctx.accounts.instructions,
+            &buyer_key,
+            current_time,
+        )?;
+
+        let terms = validate_purchase(
+            &ctx.accounts.ico_account,
+            &mut ctx.accounts.buyer_position,
+            ico_key,
+            buyer_key,
+            ctx.bumps.buyer_position,
+            ctx.accounts.token_vault.amount,
+            amount,
+            seed_proof.as_ref(),
+            ctx.accounts.seed_allocation.as_deref(),
+            kyc_tier,
+            current_time,
+        )?;
+
+        let ico = &ctx.accounts.ico_account;
+        let price = terms.usd_price()?;
+
+        let oracle_price = OraclePrice::load(&ctx.accounts.price_oracle.try_borrow_data()?)?;
+        oracle_price.validate(
+            current_time,
+            ico.max_price_staleness,
+            ico.max_confidence_bps,
+        )?;
+        let total_cost = oracle_price.lamports_for(amount, price, ico.token_decimals)?;
+
+        let transfer_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+                from: ctx.accounts.buyer.to_account_info(),
+                to: ctx.accounts.escrow.to_account_info(),
+            },
+        );
+        anchor_lang::system_program::transfer(transfer_context, total_cost)?;
+
+        let ico = &mut ctx.accounts.ico_account;
+        ico.total_raised = ico
+            .total_raised
+            .checked_add(total_cost)
+            .ok_or(IcoError::MathOverflow)?;
+        let schedule = ico.round_schedule_mut(terms.round)?;
+        schedule.lamports_raised = schedule
+            .lamports_raised
+            .checked_add(total_cost)
+            .ok_or(IcoError::MathOverflow)?;
+
+        let position = &mut ctx.accounts.buyer_position;
+        position.lamports_paid = position
+            .lamports_paid
+            .checked_add(total_cost)
+            .ok_or(IcoError::MathOverflow)?;
+
+        record_purchase(
+            &mut ctx.accounts.ico_account,
+            &mut ctx.accounts.buyer_position,
+            &mut ctx.accounts.purchase_account,
+            ctx.bumps.purchase_account,
+            ico_key,
+            buyer_key,
+            &terms,
+            amount,
+            price,
+            total_cost,
+            Pubkey::default(),
+            current_time,
+        )
+    }
+
+    pub fn buy_tokens_with_spl(
+        ctx: Context<BuyTokensWithSpl>,
+        amount: u64,
+        seed_proof: Option<SeedWhitelistProof>,
+    ) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let ico_key = ctx.accounts.ico_account.key();
+        let buyer_key = ctx.accounts.buyer.key();
+        let kyc_tier = KycAttestation::verify(
+            &ctx.accounts.ico_account,
+            &ctx.accounts.instructions,
+            &buyer_key,
+            current_time,
+        )?;
+        let payment_mint = ctx.accounts.payment_mint.key();
+
+        let terms = validate_purchase(
+            &ctx.accounts.ico_account,
+            &mut ctx.accounts.buyer_position,
+            ico_key,
+            buyer_key,
+            ctx.bumps.buyer_position,
+            ctx.accounts.token_vault.amount,
+            amount,
+            seed_proof.as_ref(),
+            ctx.accounts.seed_allocation.as_deref(),
+            kyc_tier,
+            current_time,
+        )?;
+
+        let payment = ctx
+            .accounts
+            .ico_account
+            .payment_mints
+            .iter()
+            .find(|p| p.mint == payment_mint)
+            .ok_or(IcoError::PaymentMintNotAccepted)?;
+        require!(
+            payment.treasury_token_account == ctx.accounts.payment_vault.key(),
+            IcoError::InvalidTokenAccount
+        );
+        let mut price = payment.price_for(terms.round);
+        if terms.is_seed_round {
+            price = terms.scale_seed_price(price)?;
+        }
+        require!(price > 0, IcoError::PaymentMintNotAccepted);
+        let total_cost =
+            PaymentMint::cost_for(amount, price, ctx.accounts.ico_account.token_decimals)?;
+
+        let transfer_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            token::Transfer {
+                from: ctx.accounts.buyer_payment_account.to_account_info(),
+                to: ctx.accounts.payment_vault.to_account_info(),
+                authority: ctx.accounts.buyer.to_account_info(),
+            },
+        );
+        token::transfer(transfer_ctx, total_cost)?;
+
+        let payment = ctx
+            .accounts
+            .ico_account
+            .payment_mints
+            .iter_mut()
+            .find(|p| p.mint == payment_mint)
+            .ok_or(IcoError::PaymentMintNotAccepted)?;
+        payment.total_raised = payment
+            .total_raised
+            .checked_add(total_cost)
+            .ok_or(IcoError::MathOverflow)?;
+
+        record_purchase(
+            &mut ctx.accounts.ico_account,
+            &mut ctx.accounts.buyer_position,
+            &mut ctx.accounts.purchase_account,
+            ctx.bumps.purchase_account,
+            ico_key,
+            buyer_key,
+            &terms,
+            amount,
+            price,
+            total_cost,
+            payment_mint,
+            current_time,
+        )
+    }
+
+    pub fn distribute_tokens(ctx: Context<DistributeTokens>) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let vesting_start = ctx.accounts.ico_account.sale_end()?;
+        let purchase = &mut ctx.accounts.purchase_account;
+
+        require!(!purchase.is_distributed, IcoError::AlreadyDistributed);
+        require!(
+            purchase.vested_amount(vesting_start, current_time)? == purchase.amount,
+            IcoError::TokensStillVesting
+        );
+
+        release_purchase(
+            &ctx.accounts.token_program,
+            &ctx.accounts.token_vault,
+            ctx.accounts.buyer_token_account.to_account_info(),
+            &mut ctx.accounts.ico_account,
+            ctx.bumps.ico_account,
+            purchase,
+        )?;
+
+        Ok(())
+    }
+
+    pub fn batch_distribute_tokens<'info>(
+        ctx: Context<'_, '_, 'info, 'info, BatchDistributeTokens<'info>>,
+    ) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let ico_key = ctx.accounts.ico_account.key();
+        let vesting_start = ctx.accounts.ico_account.sale_end()?;
+        let remaining_accounts = ctx.remaining_accounts;
+
+        // Each purchase account is followed by the buyer's token account
+        require!(
+            !remaining_accounts.is_empty() && remaining_accounts.len() % 2 == 0,
+            IcoError::InvalidRemainingAccounts
+        );
+
+        let mut distributed_count: u64 = 0;
+        let mut skipped_count: u64 = 0;
+        let mut total_amount: u64 = 0;
+
+        for pair in remaining_accounts.chunks(2) {
+            let (purchase_info, buyer_token_info) = (&pair[0], &pair[1]);
+
+            require!(purchase_info.is_writable, IcoError::InvalidPurchase);
+            let mut purchase = Account::<PurchaseAccount>::try_from(purchase_info)?;
+            require!(purchase.ico == ico_key, IcoError::InvalidPurchase);
+            let expected_purchase = Pubkey::create_program_address(
+                &[
+                    b"purchase",
+                    ico_key.as_ref(),
+                    purchase.buyer.as_ref(),
+                    &purchase.index.to_le_bytes(),
+                    &[purchase.bump],
+                ],
+                ctx.program_id,
+            )
+            .map_err(|_| error!(IcoError::InvalidPurchase))?;
+            require_keys_eq!(
+                expected_purchase,
+                purchase_info.key(),
+                IcoError::InvalidPurchase
+            );
+
+            let buyer_token_account = Account::<TokenAccount>::try_from(buyer_token_info)?;
+            require!(
+                buyer_token_account.owner == purchase.buyer,
+                IcoError::InvalidTokenAccount
+            );
+            require!(
+                buyer_token_account.mint == ctx.accounts.ico_account.token_mint,
+                IcoError::InvalidTokenMint
+            );
+
+            if purchase.is_distributed
+                || purchase.vested_amount(vesting_start, current_time)? != purchase.amount
+            {
+                skipped_count = skipped_count.checked_add(1).ok_or(IcoError::MathOverflow)?;
+                continue;
+            }
+
+            let remaining = release_purchase(
+                &ctx.accounts.token_program,
+                &ctx.accounts.token_vault,
+                buyer_token_info.clone(),
+                &mut ctx.accounts.ico_account,
+                ctx.bumps.ico_account,
+                &mut purchase,
+            )?;
+            purchase.exit(ctx.program_id)?;
+
+            distributed_count = distributed_count
+                .checked_add(1)
+                .ok_or(IcoError::MathOverflow)?;
+            total_amount = total_amount
+                .checked_add(remaining)
+                .ok_or(IcoError::MathOverflow)?;
+        }
+
+        emit!(BatchDistributionEvent {
+            distributed_count,
+            skipped_count,
+            total_amount,
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+
+    pub fn claim_tokens(ctx: Context<ClaimTokens>) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let vesting_start = ctx.accounts.ico_account.sale_end()?;
+        let purchase = &mut ctx.accounts.purchase_account;
+
+        require!(!purchase.is_distributed, IcoError::AlreadyDistributed);
+        require!(
+            purchase.vested_amount(vesting_start, current_time)? == purchase.amount,
+            IcoError::TokensStillVesting
+        );
+
+        release_purchase(
+            &ctx.accounts.token_program,
+            &ctx.accounts.token_vault,
+            ctx.accounts.buyer_token_account.to_account_info(),
+            &mut ctx.accounts.ico_account,
+            ctx.bumps.ico_account,
+            purchase,
+        )?;
+
+        Ok(())
+    }
+
+    pub fn claim_vested(ctx: Context<ClaimTokens>) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let vesting_start = ctx.accounts.ico_account.sale_end()?;
+        let purchase = &mut ctx.accounts.purchase_account;
+
+        let claimable = purchase
+            .vested_amount(vesting_start, current_time)?
+            .checked_sub(purchase.claimed_amount)
+            .ok_or(IcoError::MathOverflow)?;
+        require!(claimable > 0, IcoError::NothingToClaim);
+
+        transfer_from_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.token_vault,
+            ctx.accounts.buyer_token_account.to_account_info(),
+            &ctx.accounts.ico_account,
+            ctx.bumps.ico_account,
+            claimable,
+        )?;
+
+        purchase.claimed_amount = purchase
+            .claimed_amount
+            .checked_add(claimable)
+            .ok_or(IcoError::MathOverflow)?;
+        purchase.is_distributed = purchase.claimed_amount == purchase.amount;
+
+        let ico = &mut ctx.accounts.ico_account;
+        ico.tokens_distributed = ico
+            .tokens_distributed
+            .checked_add(claimable)
+            .ok_or(IcoError::MathOverflow)?;
+
+        emit!(VestedTokensClaimedEvent {
+            buyer: purchase.buyer,
+            amount: claimable,
+            total_claimed: purchase.claimed_amount,
+            round: purchase.round,
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+
+    pub fn close_purchase(_ctx: Context<ClosePurchase>) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn end_ico(ctx: Context<EndIco>) -> Result<()> {
+        let ico = &mut ctx.accounts.ico_account;
+        let current_time = Clock::get()?.unix_timestamp;
+        require!(
+            current_time >= ico.start_time + ico.duration,
+            IcoError::IcoStillActive
+        );
+        require!(ico.is_active, IcoError::IcoNotActive);
+
+        let soft_cap_reached = ico.close_sale();
+        if soft_cap_reached {
+            transfer_from_escrow(
+                &ctx.accounts.system_program,
+                &ctx.accounts.escrow,
+                ctx.accounts.treasury_wallet.to_account_info(),
+                ico.key(),
+                ctx.bumps.escrow,
+                ico.total_raised,
+            )?;
+        }
+
+        emit!(IcoEndedEvent {
+            total_raised: ico.total_raised,
+            usd_raised: ico.usd_raised,
+            soft_cap: ico.soft_cap,
+            soft_cap_reached,
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+
+    pub fn settle_round(ctx: Context<SettleRound>, round: RoundType) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        let ico = &ctx.accounts.ico_account;
+        let index = ico
+            .rounds
+            .iter()
+            .position(|r| r.round == round)
+            .ok_or(IcoError::RoundNotScheduled)?;
+        let schedule = ico.rounds[index];
+        require!(
+            current_time >= schedule.end_time,
+            IcoError::RoundStillActive
+        );
+        require!(!schedule.is_settled, IcoError::RoundAlreadySettled);
+        // Settling in schedule order keeps carried-forward tokens from landing in a settled round
+        require!(
+            ico.rounds[..index].iter().all(|r| r.is_settled),
+            IcoError::EarlierRoundNotSettled
+        );
+
+        let unsold = schedule
+            .allocation
+            .checked_sub(schedule.tokens_sold)
+            .ok_or(IcoError::MathOverflow)?;
+
+        // Tokens owed to buyers or still on offer in later rounds never leave the vault
+        let outstanding = ico
+            .tokens_sold
+            .checked_sub(ico.tokens_distributed)
+            .ok_or(IcoError::MathOverflow)?;
+        let reserved = ico.rounds[index + 1..]
+            .iter()
+            .try_fold(outstanding, |total, r| {
+                total.checked_add(r.allocation.saturating_sub(r.tokens_sold))
+            })
+            .ok_or(IcoError::MathOverflow)?;
+        let surplus = ctx.accounts.token_vault.amount.saturating_sub(reserved);
+        // Moving less than the unsold share would quietly drop the rest from every
+        // round, so an underfunded vault has to be topped up before settling
+        if schedule.rollover != RolloverPolicy::CarryForward {
+            require!(unsold <= surplus, IcoError::InsufficientVaultBalance);
+        }
+
+        let (amount_moved, destination) = match schedule.rollover {
+            RolloverPolicy::CarryForward => {
+                let next = ico
+                    .rounds
+                    .get(index + 1)
+                    .ok_or(IcoError::InvalidRoundSchedule)?;
+                (unsold, Some(next.round))
+            }
+            RolloverPolicy::ReturnToReserve => {
+                if unsold > 0 {
+                    let reserve = ctx
+                        .accounts
+                        .reserve_token_account
+                        .as_ref()
+                        .ok_or(IcoError::InvalidTokenAccount)?;
+                    transfer_from_vault(
+                        &ctx.accounts.token_program,
+                        &ctx.accounts.token_vault,
+                        reserve.to_account_info(),
+                        &ctx.accounts.ico_account,
+                        ctx.bumps.ico_account,
+                        unsold,
+                    )?;
+                }
+                (unsold, None)
+            }
+            RolloverPolicy::Burn => {
+                if unsold > 0 {
+                    let signer_seeds: &[&[&[u8]]] =
+                        &[&[b"ico", ico.token_mint.as_ref(), &[ctx.bumps.ico_account]]];
+                    let burn_ctx = CpiContext::new_with_signer(
+                        ctx.accounts.token_program.to_account_info(),
+                        token::Burn {
+                            mint: ctx.accounts.token_mint.to_account_info(),
+                            from: ctx.accounts.token_vault.to_account_info(),
+                            authority: ctx.accounts.ico_account.to_account_info(),
+                        },
+                        signer_seeds,
+                    );
+                    token::burn(burn_ctx, unsold)?;
+                }
+                (unsold, None)
+            }
+        };
+
+        // The round closes at what it sold; its unsold share leaves it either way
+        let ico = &mut ctx.accounts.ico_account;
+        ico.rounds[index].allocation = schedule.tokens_sold;
+        ico.rounds[index].is_settled = true;
+        match schedule.rollover {
+            RolloverPolicy::CarryForward => {
+                let next = &mut ico.rounds[index + 1];
+                next.allocation = next
+                    .allocation
+                    .checked_add(unsold)
+                    .ok_or(IcoError::MathOverflow)?;
+            }
+            RolloverPolicy::Burn => {
+                ico.total_supply = ico
+                    .total_supply
+                    .checked_sub(amount_moved)
+                    .ok_or(IcoError::MathOverflow)?;
+            }
+            RolloverPolicy::ReturnToReserve => {}
+        }
+
+        emit!(RoundRolloverEvent {
+            round,
+            policy: schedule.rollover,
+            unsold,
+            amount_moved,
+            destination,
+            timestamp: current_time,
+        });
+
+        Ok(())
+    }
+
+    pub fn withdraw_payment_tokens(ctx: Context<WithdrawPaymentTokens>) -> Result<()> {
+        let amount = ctx.accounts.payment_vault.amount;
+
+        transfer_from_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.payment_vault,
+            ctx.accounts.treasury_token_account.to_account_info(),
+            &ctx.accounts.ico_account,
+            ctx.bumps.ico_account,
+            amount,
+        )?;
+
+        emit!(PaymentTokensWithdrawnEvent {
+            mint: ctx.accounts.payment_vault.mint,
+            amount,
+            destination: ctx.accounts.treasury_token_account.key(),
+        });
+
+        Ok(())
+    }
+
+    pub fn refund(ctx: Context<Refund>) -> Result<()> {
+        let purchase = &ctx.accounts.purchase_account;
+
+        transfer_from_escrow(
+            &ctx.accounts.system_program,
+            &ctx.accounts.escrow,
+            ctx.accounts.buyer.to_account_info(),
+            ctx.accounts.ico_account.key(),
+            ctx.bumps.escrow,
+            purchase.total_cost,
+        )?;
+        ctx.accounts.ico_account.release_refunded(purchase)?;
+
+        emit!(RefundEvent {
+            buyer: purchase.buyer,
+            amount: purchase.total_cost,
+            tokens: purchase.amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn refund_spl(ctx: Context<RefundSpl>) -> Result<()> {
+        let purchase = &ctx.accounts.purchase_account;
+
+        transfer_from_vault(
+            &ctx.accounts.token_program,
+            &ctx.accounts.payment_vault,
+            ctx.accounts.buyer_payment_account.to_account_info(),
+            &ctx.accounts.ico_account,
+            ctx.bumps.ico_account,
+            purchase.total_cost,
+        )?;
+        ctx.accounts.ico_account.release_refunded(purchase)?;
+
+        emit!(RefundEvent {
+            buyer: purchase.buyer,
+            amount: purchase.total_cost,
+            tokens: purchase.amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
+
+/// Applies a parameter update, enforcing the lifecycle guardrails shared by the
+/// authority path and executed council proposals.
+fn apply_ico_parameters(
+    ico: &mut IcoAccount,
+    params: &IcoParametersUpdate,
+    current_time: i64,
+) -> Result<()> {
+    require!(ico.is_active, IcoError::IcoNotActive);
+    require!(
+        ico.start_time + ico.duration > current_time,
+        IcoError::InvalidSaleWindow
+    );
+
+    let mut changes: Vec<(IcoParameter, u64, u64)> = Vec::new();
+
+    if let Some(total_supply) = params.total_supply {
+        require!(total_supply >= ico.tokens_sold, IcoError::BelowAmountSold);
+        changes.push((IcoParameter::TotalSupply, ico.total_supply, total_supply));
+        ico.total_supply = total_supply;
+    }
+
+    if let Some(seed_price) = params.seed_price {
+        require!(seed_price > 0, IcoError::InvalidPrice);
+        let schedule = ico.round_schedule_mut(RoundType::Seed)?;
+        changes.push((IcoParameter::SeedPrice, schedule.price, seed_price));
+        schedule.price = seed_price;
+    }
+
+    if let Some(private_price) = params.private_price {
+        require!(private_price > 0, IcoError::InvalidPrice);
+        let schedule = ico.round_schedule_mut(RoundType::Private)?;
+        changes.push((IcoParameter::PrivatePrice, schedule.price, private_price));
+        schedule.price = private_price;
+    }
+
+    if let Some(pre_ico_price) = params.pre_ico_price {
+        require!(pre_ico_price > 0, IcoError::InvalidPrice);
+        let schedule = ico.round_schedule_mut(RoundType::PreICO)?;
+        changes.push((IcoParameter::PreIcoPrice, schedule.price, pre_ico_price));
+        schedule.price = pre_ico_price;
+    }
+
+    if let Some(public_price) = params.public_price {
+        require!(public_price > 0, IcoError::InvalidPrice);
+        let schedule = ico.round_schedule_mut(RoundType::PublicICO)?;
+        changes.push((IcoParameter::PublicPrice, schedule.price, public_price));
+        schedule.price = public_price;
+    }
+
+    if let Some(allocation) = params.seed_round_allocation {
+        let schedule = ico.round_schedule_mut(RoundType::Seed)?;
+        require!(!schedule.is_settled, IcoError::RoundAlreadySettled);
+        require!(
+            allocation >= schedule.tokens_sold,
+            IcoError::BelowAmountSold
+        );
+        changes.push((
+            IcoParameter::SeedRoundAllocation,
+            schedule.allocation,
+            allocation,
+        ));
+        schedule.allocation = allocation;
+    }
+
+    if let Some(allocation) = params.private_allocation {
+        let schedule = ico.round_schedule_mut(RoundType::Private)?;
+        require!(!schedule.is_settled, IcoError::RoundAlreadySettled);
+        require!(
+            allocation >= schedule.tokens_sold,
+            IcoError::BelowAmountSold
+        );
+        changes.push((
+            IcoParameter::PrivateAllocation,
+            schedule.allocation,
+            allocation,
+        ));
+        schedule.allocation = allocation;
+    }
+
+    if let Some(allocation) = params.pre_ico_allocation {
+        let schedule = ico.round_schedule_mut(RoundType::PreICO)?;
+        require!(!schedule.is_settled, IcoError::RoundAlreadySettled);
+        require!(
+            allocation >= schedule.tokens_sold,
+            IcoError::BelowAmountSold
+        );
+        changes.push((
+            IcoParameter::PreIcoAllocation,
+            schedule.allocation,
+            allocation,
+        ));
+        schedule.allocation = allocation;
+    }
+
+    if let Some(allocation) = params.public_round_allocation {
+        let schedule = ico.round_schedule_mut(RoundType::PublicICO)?;
+        require!(!schedule.is_settled, IcoError::RoundAlreadySettled);
+        require!(
+            allocation >= schedule.tokens_sold,
+            IcoError::BelowAmountSold
+        );
+        changes.push((
+            IcoParameter::PublicRoundAllocation,
+            schedule.allocation,
+            allocation,
+        ));
+        schedule.allocation = allocation;
+    }
+
+    require!(
+        ico.allocated_supply()? <= ico.total_supply,
+        IcoError::AllocationExceedsSupply
+    );
+
+    if let Some(duration) = params.duration {
+        require!(duration > 0, IcoError::InvalidSaleWindow);
+        let last = ico.rounds.last().ok_or(IcoError::InvalidRoundSchedule)?;
+        let (round, start_time) = (last.round, last.start_time);
+        let end_time = ico
+            .start_time
+            .checked_add(duration)
+            .ok_or(IcoError::MathOverflow)?;
+        apply_round(ico, round, start_time, end_time, current_time)?;
+    }
+
+    for (parameter, old_value, new_value) in changes {
+        emit!(IcoParameterUpdatedEvent {
+            parameter,
+            old_value,
+            new_value,
+            timestamp: current_time,
+        });
+    }
+
+    Ok(())
+}
+
+/// Reschedules one round. Finished rounds are history and a running round keeps
+/// its announced opening; the sale window follows the updated schedule.
+fn apply_round(
+    ico: &mut IcoAccount,
+    round: RoundType,
+    start_time: i64,
+    end_time: i64,
+    current_time: i64,
+) -> Result<()> {
+    let mut rounds = ico.rounds.clone();
+    let schedule = rounds
+        .iter_mut()
+        .find(|r| r.round == round)
+        .ok_or(IcoError::RoundNotScheduled)?;
+
+    require!(
+        schedule.end_time > current_time,
+        IcoError::InvalidSaleWindow
+    );
+    if schedule.start_time <= current_time {
+        require!(
+            start_time == schedule.start_time,
+            IcoError::SaleAlreadyStarted
+        );
+    } else {
+        require!(start_time >= current_time, IcoError::InvalidSaleWindow);
+    }
+    require!(end_time > current_time, IcoError::InvalidSaleWindow);
+
+    schedule.start_time = start_time;
+    schedule.end_time = end_time;
+    RoundSchedule::validate_schedule(&rounds)?;
+
+    let (old_start_time, old_duration) = (ico.start_time, ico.duration);
+    ico.rounds = rounds;
+    ico.sync_sale_window()?;
+
+    if ico.start_time != old_start_time {
+        emit!(IcoParameterUpdatedEvent {
+            parameter: IcoParameter::StartTime,
+            old_value: old_start_time as u64,
+            new_value: ico.start_time as u64,
+            timestamp: current_time,
+        });
+    }
+    if ico.duration != old_duration {
+        emit!(IcoParameterUpdatedEvent {
+            parameter: IcoParameter::Duration,
+            old_value: old_duration as u64,
+            new_value: ico.duration as u64,
+            timestamp: current_time,
+        });
+    }
+
+    emit!(RoundUpdateEvent {
+        round,
+        start_time,
+        end_time,
+        timestamp: current_time,
+    });
+
+    Ok(())
+}
+
+fn apply_role_grant(
+    ico: &mut IcoAccount,
+    role: Role,
+    account: Pubkey,
+    current_time: i64,
+) -> Result<()> {
+    require!(account != Pubkey::default(), IcoError::InvalidRoleHolder);
+    *ico.role_holder_mut(role) = account;
+
+    emit!(RoleGrantedEvent {
+        role,
+        account,
+        timestamp: current_time,
+    });
+
+    Ok(())
+}
+
+fn apply_role_revoke(ico: &mut IcoAccount, role: Role, current_time: i64) -> Result<()> {
+    let holder = ico.role_holder_mut(role);
+    let account = *holder;
+    *holder = Pubkey::default();
+
+    emit!(RoleRevokedEvent {
+        role,
+        account,
+        timestamp: current_time,
+    });
+
+    Ok(())
+}
+
+fn apply_vesting(
+    ico: &mut IcoAccount,
+    vesting: [VestingSchedule; 4],
+    current_time: i64,
+) -> Result<()> {
+    for schedule in &vesting {
+        schedule.validate()?;
+    }
+
+    let [seed_vesting, private_vesting, pre_ico_vesting, public_vesting] = vesting;
+    ico.seed_vesting = seed_vesting;
+    ico.private_vesting = private_vesting;
+    ico.pre_ico_vesting = pre_ico_vesting;
+    ico.public_vesting = public_vesting;
+
+    emit!(VestingConfiguredEvent {
+        seed_vesting,
+        private_vesting,
+        pre_ico_vesting,
+        public_vesting,
+        timestamp: current_time,
+    });
+
+    Ok(())
+}
+
+fn apply_seed_whitelist(
+    ico: &mut IcoAccount,
+    merkle_root: [u8; 32],
+    current_time: i64,
+) -> Result<()> {
+    ico.seed_merkle_root = merkle_root;
+
+    emit!(SeedWhitelistConfiguredEvent {
+        merkle_root,
+        timestamp: current_time,
+    });
+
+    Ok(())
+}
+
+fn apply_seed_investor(
+    ico: &Account<IcoAccount>,
+    seed_allocation: &mut SeedAllocation,
+    bump: u8,
+    investor: Pubkey,
+    allocation: u64,
+    price: u64,
+    current_time: i64,
+) -> Result<()> {
+    require!(
+        allocation > 0 && allocation <= ico.round_schedule(RoundType::Seed)?.allocation,
+        IcoError::InvalidSeedAllocation
+    );
+    require!(price > 0, IcoError::InvalidPrice);
+
+    seed_allocation.ico = ico.key();
+    seed_allocation.investor = investor;
+    seed_allocation.allocation = allocation;
+    seed_allocation.price = price;
+    seed_allocation.bump = bump;
+
+    emit!(SeedInvestorAddedEvent {
+        investor,
+        allocation,
+        price,
+        timestamp: current_time,
+    });
+
+    Ok(())
+}
+
+/// `prices` are the seed, private, pre-ICO and public prices, in that order.
+fn apply_payment_mint(
+    ico: &mut IcoAccount,
+    mint: Pubkey,
+    payment_vault: Pubkey,
+    prices: [u64; 4],
+) -> Result<()> {
+    require!(
+        mint != ico.token_mint && !ico.payment_mints.iter().any(|p| p.mint == mint),
+        IcoError::PaymentMintAlreadyExists
+    );
+    require!(
+        ico.payment_mints.len() < MAX_PAYMENT_MINTS,
+        IcoError::TooManyPaymentMints
+    );
+
+    let [seed_price, private_price, pre_ico_price, public_price] = prices;
+    ico.payment_mints.push(PaymentMint {
+        mint,
+        treasury_token_account: payment_vault,
+        seed_price,
+        private_price,
+        pre_ico_price,
+        public_price,
+        total_raised: 0,
+    });
+
+    emit!(PaymentMintAddedEvent {
+        mint,
+        treasury_token_account: payment_vault,
+        seed_price,
+        private_price,
+        pre_ico_price,
+        public_price,
+    });
+
+    Ok(())
+}
+
+fn apply_price_oracle(
+    ico: &mut IcoAccount,
+    price_oracle: &AccountInfo,
+    oracle_program: Pubkey,
+    max_price_staleness: i64,
+    max_confidence_bps: u16,
+) -> Result<()> {
+    require!(
+        max_price_staleness > 0 && max_confidence_bps <= 10000,
+        IcoError::InvalidOracleConfig
+    );
+    require_keys_eq!(*price_oracle.owner, oracle_program, IcoError::InvalidOracle);
+
+    // Make sure the account actually parses as a price feed before trusting it
+    OraclePrice::load(&price_oracle.try_borrow_data()?)?;
+
+    ico.price_oracle = price_oracle.key();
+    ico.oracle_program = oracle_program;
+    ico.max_price_staleness = max_price_staleness;
+    ico.max_confidence_bps = max_confidence_bps;
+
+    emit!(PriceOracleConfiguredEvent {
+        price_oracle: ico.price_oracle,
+        oracle_program,
+        max_price_staleness,
+        max_confidence_bps,
+    });
+
+    Ok(())
+}
+
+fn apply_purchase_limits(
+    ico: &mut IcoAccount,
+    limits: [PurchaseLimits; 4],
+    current_time: i64,
+) -> Result<()> {
+    for round_limits in &limits {
+        round_limits.validate()?;
+    }
+
+    let [seed_limits, private_limits, pre_ico_limits, public_limits] = limits;
+    ico.seed_limits = seed_limits;
+    ico.private_limits = private_limits;
+    ico.pre_ico_limits = pre_ico_limits;
+    ico.public_limits = public_limits;
+
+    emit!(PurchaseLimitsConfiguredEvent {
+        seed_limits,
+        private_limits,
+        pre_ico_limits,
+        public_limits,
+        timestamp: current_time,
+    });
+
+    Ok(())
+}
+
+fn apply_kyc(
+    ico: &mut IcoAccount,
+    kyc_signer: Pubkey,
+    tier_wallet_bps: [u16; KYC_TIER_COUNT],
+    current_time: i64,
+) -> Result<()> {
+    ico.kyc_signer = kyc_signer;
+    ico.kyc_tier_wallet_bps = tier_wallet_bps;
+
+    emit!(KycConfiguredEvent {
+        kyc_signer,
+        tier_wallet_bps,
+        timestamp: current_time,
+    });
+
+    Ok(())
+}
+
+fn transfer_from_vault<'info>(
+    token_program: &Program<'info, token::Token>,
+    vault: &Account<'info, TokenAccount>,
+    to: AccountInfo<'info>,
+    ico_account: &Account<'info, IcoAccount>,
+    ico_bump: u8,
+    amount: u64,
+) -> Result<()> {
+    let signer_seeds: &[&[&[u8]]] = &[&[b"ico", ico_account.token_mint.as_ref(), &[ico_bump]]];
+    let transfer_ctx = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        token::Transfer {
+            from: vault.to_account_info(),
+            to,
+            authority: ico_account.to_account_info(),
+        },
+        signer_seeds,
+    );
+
+    token::transfer(transfer_ctx, amount)
+}
+
+/// Pays out what is left of a fully vested purchase, marks it distributed and
+/// returns the amount sent.
+fn release_purchase<'info>(
+    token_program: &Program<'info, token::Token>,
+    vault: &Account<'info, TokenAccount>,
+    to: AccountInfo<'info>,
+    ico_account: &mut Account<'info, IcoAccount>,
+    ico_bump: u8,
+    purchase: &mut PurchaseAccount,
+) -> Result<u64> {
+    let remaining = purchase
+        .amount
+        .checked_sub(purchase.claimed_amount)
+        .ok_or(IcoError::MathOverflow)?;
+
+    transfer_from_vault(token_program, vault, to, ico_account, ico_bump, remaining)?;
+
+    purchase.claimed_amount = purchase.amount;
+    purchase.is_distributed = true;
+    ico_account.tokens_distributed = ico_account
+        .tokens_distributed
+        .checked_add(remaining)
+        .ok_or(IcoError::MathOverflow)?;
+
+    emit!(TokenDistributionEvent {
+        buyer: purchase.buyer,
+        amount: remaining,
+        round: purchase.round,
+    });
+
+    Ok(remaining)
+}
+
+fn transfer_from_escrow<'info>(
+    system_program: &Program<'info, System>,
+    escrow: &SystemAccount<'info>,
+    to: AccountInfo<'info>,
+    ico_key: Pubkey,
+    escrow_bump: u8,
+    amount: u64,
+) -> Result<()> {
+    let signer_seeds: &[&[&[u8]]] = &[&[b"escrow", ico_key.as_ref(), &[escrow_bump]]];
+    let transfer_ctx = CpiContext::new_with_signer(
+        system_program.to_account_info(),
+        anchor_lang::system_program::Transfer {
+            from: escrow.to_account_info(),
+            to,
+        },
+        signer_seeds,
+    );
+
+    anchor_lang::system_program::transfer(transfer_ctx, amount)
+}
+
+const PYTH_MAGIC: u32 = 0xa1b2c3d4;
+const PYTH_PRICE_ACCOUNT: u32 = 3;
+const PYTH_STATUS_TRADING: u32 = 1;
+
+/// Aggregate SOL/USD price read from a Pyth-style (v2 layout) price account.
+pub struct OraclePrice {
+    pub price: i64,
+    pub conf: u64,
+    pub expo: i32,
+    pub publish_time: i64,
+}
+
+impl OraclePrice {
+    pub fn load(data: &[u8]) -> Result<Self> {
+        let read = |offset: usize, len: usize| -> Result<&[u8]> {
+            data.get(offset..offset + len)
+                .ok_or_else(|| error!(IcoError::InvalidOracle))
+        };
+        let read_u32 = |offset| -> Result<u32> {
+            Ok(u32::from_le_bytes(read(offset, 4)?.try_into().unwrap()))
+        };
+        let read_i64 = |offset| -> Result<i64> {
+            Ok(i64::from_le_bytes(read(offset, 8)?.try_into().unwrap()))
+        };
+
+        require!(
+            read_u32(0)? == PYTH_MAGIC && read_u32(8)? == PYTH_PRICE_ACCOUNT,
+            IcoError::InvalidOracle
+        );
+        require!(
+            read_u32(224)? == PYTH_STATUS_TRADING,
+            IcoError::OraclePriceUnavailable
+        );
+
+        let price = OraclePrice {
+            expo: read_u32(20)? as i32,
+            publish_time: read_i64(96)?,
+            price: read_i64(208)?,
+            conf: read_i64(216)? as u64,
+        };
+        require!(price.price > 0, IcoError::OraclePriceUnavailable);
+
+        Ok(price)
+    }
+
+    pub fn validate(&self, now: i64, max_staleness: i64, max_confidence_bps: u16) -> Result<()> {
+        require!(
+            now.saturating_sub(self.publish_time) <= max_staleness,
+            IcoError::StaleOraclePrice
+        );
+        require!(
+            (self.conf as u128) * 10000 <= (self.price as u128) * max_confidence_bps as u128,
+            IcoError::OracleConfidenceTooWide
+        );
+        Ok(())
+    }
+
+    /// Lamports owed for `amount` base units of a token priced at `usd_micro_price`
+    /// (millionths of a dollar per whole token), rounded up in the sale's favour.
+    pub fn lamports_for(
+        &self,
+        amount: u64,
+        usd_micro_price: u64,
+        token_decimals: u8,
+    ) -> Result<u64> {
+        let pow10 = |exp: u32| 10u128.checked_pow(exp).ok_or(IcoError::MathOverflow);
+
+        // lamports = amount * usd_micro * 1e9 / (10^decimals * 1e6 * price * 10^expo)
+        let mut numerator = (amount as u128)
+            .checked_mul(usd_micro_price as u128)
+            .ok_or(IcoError::MathOverflow)?
+            .checked_mul(1000)
+            .ok_or(IcoError::MathOverflow)?;
+        let mut denominator = pow10(token_decimals as u32)?
+            .checked_mul(self.price as u128)
+            .ok_or(IcoError::MathOverflow)?;
+        if self.expo < 0 {
+            numerator = numerator
+                .checked_mul(pow10(self.expo.unsigned_abs())?)
+                .ok_or(IcoError::MathOverflow)?;
+        } else {
+            denominator = denominator
+                .checked_mul(pow10(self.expo as u32)?)
+                .ok_or(IcoError::MathOverflow)?;
+        }
+
+        let lamports = numerator
+            .checked_add(denominator - 1)
+            .ok_or(IcoError::MathOverflow)?
+            / denominator;
+        u64::try_from(lamports).map_err(|_| error!(IcoError::MathOverflow))
+    }
+}
+
+pub const KYC_TIER_COUNT: usize = 4;
+const KYC_ATTESTATION_LEN: usize = 32 + 8 + 1;
+
+/// KYC attestation signed off-chain by the configured KYC signer and checked
+/// through an ed25519 verify instruction placed right before the purchase.
+pub struct KycAttestation {
+    pub buyer: Pubkey,
+    pub expiry: i64,
+    pub tier: u8,
+}
+
+impl KycAttestation {
+    /// Returns the buyer's attested tier, or `None` when no KYC signer is configured.
+    pub fn verify(
+        ico: &IcoAccount,
+        instructions: &AccountInfo,
+        buyer: &Pubkey,
+        current_time: i64,
+    ) -> Result<Option<u8>> {
+        if ico.kyc_signer == Pubkey::default() {
+            return Ok(None);
+        }
+
+        let attestation = Self::load(instructions, &ico.kyc_signer)?;
+        require_keys_eq!(attestation.buyer, *buyer, IcoError::InvalidKycAttestation);
+        require!(
+            attestation.expiry > current_time,
+            IcoError::KycAttestationExpired
+        );
+        require!(
+            (attestation.tier as usize) < KYC_TIER_COUNT,
+            IcoError::InvalidKycAttestation
+        );
+
+        Ok(Some(attestation.tier))
+    }
+
+    /// Reads the attestation from the ed25519 verify instruction preceding this one.
+    /// The runtime has already checked the signature; here we make sure it was made
+    /// by `kyc_signer` over a well-formed (buyer, expiry, tier) message.
+    fn load(instructions: &AccountInfo, kyc_signer: &Pubkey) -> Result<Self> {
+        let current_index = load_current_index_checked(instructions)?;
+        require!(current_index > 0, IcoError::MissingKycAttestation);
+        let ix = load_instruction_at_checked(current_index as usize - 1, instructions)?;
+        require_keys_eq!(
+            ix.program_id,
+            ed25519_program::ID,
+            IcoError::MissingKycAttestation
+        );
+
+        // Header: signature count, padding, then one set of u16 offsets
+        let data = &ix.data;
+        require!(
+            data.len() >= 16 && data[0] == 1,
+            IcoError::InvalidKycAttestation
+        );
+        let read_u16 = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+
+        // Signature, key and message must all live in the verify instruction itself
+        require!(
+            read_u16(4) == u16::MAX && read_u16(8) == u16::MAX && read_u16(14) == u16::MAX,
+            IcoError::InvalidKycAttestation
+        );
+
+        let key_offset = read_u16(6) as usize;
+        let signer = data
+            .get(key_offset..key_offset + 32)
+            .ok_or(IcoError::InvalidKycAttestation)?;
+        require!(
+            signer == kyc_signer.as_ref(),
+            IcoError::InvalidKycAttestation
+        );
+
+        let message_offset = read_u16(10) as usize;
+        require!(
+            read_u16(12) as usize == KYC_ATTESTATION_LEN,
+            IcoError::InvalidKycAttestation
+        );
+        let message = data
+            .get(message_offset..message_offset + KYC_ATTESTATION_LEN)
+            .ok_or(IcoError::InvalidKycAttestation)?;
+
+        Ok(KycAttestation {
+            buyer: Pubkey::try_from(&message[0..32]).unwrap(),
+            expiry: i64::from_le_bytes(message[32..40].try_into().unwrap()),
+            tier: message[40],
+        })
+    }
+}
+
+/// Round and schedule a purchase is booked under, resolved by `validate_purchase`.
+struct PurchaseTerms {
+    is_seed_round: bool,
+    round: RoundType,
+    round_price: u64,
+    vesting: VestingSchedule,
+    is_new_investor: bool,
+    is_new_round_buyer: bool,
+    // Individual seed pricing, as a ratio applied to the round's seed price
+    seed_price_numerator: u64,
+    seed_price_denominator: u64,
+}
+
+impl PurchaseTerms {
+    /// USD micro-units per whole token this buyer pays, whatever the payment method.
+    fn usd_price(&self) -> Result<u64> {
+        if self.is_seed_round {
+            self.scale_seed_price(self.round_price)
+        } else {
+            Ok(self.round_price)
+        }
+    }
+
+    fn scale_seed_price(&self, seed_price: u64) -> Result<u64> {
+        require!(self.seed_price_denominator > 0, IcoError::InvalidPrice);
+        let scaled = (seed_price as u128)
+            .checked_mul(self.seed_price_numerator as u128)
+            .ok_or(IcoError::MathOverflow)?
+            / self.seed_price_denominator as u128;
+        u64::try_from(scaled).map_err(|_| error!(IcoError::MathOverflow))
+    }
+}
+
+/// Leaf data and sibling hashes proving a buyer is on the published seed whitelist.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct SeedWhitelistProof {
+    pub max_allocation: u64,
+    pub price_tier_bps: u16, // seed price charged to this investor, in bps of the round's seed price
+    pub proof: Vec<[u8; 32]>,
+}
+
+impl SeedWhitelistProof {
+    /// Leaves are `keccak(0x00 || investor || max_allocation || price_tier_bps)` and
+    /// inner nodes `keccak(0x01 || min(a, b) || max(a, b))`, so a leaf can never be
+    /// passed off as an inner node.
+    pub fn verify(&self, root: &[u8; 32], investor: &Pubkey) -> bool {
+        let mut node = keccak::hashv(&[
+            &[0u8],
+            investor.as_ref(),
+            &self.max_allocation.to_le_bytes(),
+            &self.price_tier_bps.to_le_bytes(),
+        ])
+        .to_bytes();
+
+        for sibling in &self.proof {
+            let (left, right) = if node <= *sibling {
+                (node, *sibling)
+            } else {
+                (*sibling, node)
+            };
+            node = keccak::hashv(&[&[1u8], &left, &right]).to_bytes();
+        }
+
+        node == *root
+    }
+}
+
+/// Checks a purchase against the sale window, round allocation, wallet limits,
+/// hard cap and vault backing, and adds it to the buyer's position.
+#[allow(clippy::too_many_arguments)]
+fn validate_purchase(
+    ico: &IcoAccount,
+    position: &mut BuyerPosition,
+    ico_key: Pubkey,
+    buyer: Pubkey,
+    position_bump: u8,
+    vault_balance: u64,
+    amount: u64,
+    seed_proof: Option<&SeedWhitelistProof>,
+    seed_allocation: Option<&SeedAllocation>,
+    kyc_tier: Option<u8>,
+    current_time: i64,
+) -> Result<PurchaseTerms> {
+    require!(
+        ico.is_active
+            && current_time >= ico.start_time
+            && current_time < ico.start_time + ico.duration,
+        IcoError::IcoNotActive
+    );
+    require!(!ico.is_paused, IcoError::SalePaused);
+    let active_round = *ico
+        .active_round(current_time)
+        .ok_or(IcoError::NoActiveRound)?;
+
+    let is_seed_round = active_round.round == RoundType::Seed;
+
+    // Seed round buyers need an individually negotiated deal, proven either with a
+    // merkle proof or with the SeedAllocation account created for them
+    let (investor_allocation, seed_price_numerator, seed_price_denominator) = if is_seed_round {
+        match (seed_proof, seed_allocation) {
+            (Some(seed_proof), _) => {
+                require!(
+                    ico.seed_merkle_root != [0u8; 32]
+                        && seed_proof.verify(&ico.seed_merkle_root, &buyer),
+                    IcoError::InvalidMerkleProof
+                );
+                require!(seed_proof.price_tier_bps > 0, IcoError::InvalidMerkleProof);
+                (
+                    Some(seed_proof.max_allocation),
+                    seed_proof.price_tier_bps as u64,
+                    10000,
+                )
+            }
+            (None, Some(seed_allocation)) => {
+                // The allocation is checked against the position's seed total, which
+                // survives the deal being removed and added again
+                (
+                    Some(seed_allocation.allocation),
+                    seed_allocation.price,
+                    active_round.price,
+                )
+            }
+            (None, None) => return err!(IcoError::SeedDealRequired),
+        }
+    } else {
+        (None, 1, 1)
+    };
+
+    // Each round is measured against its own sales only, plus whatever earlier
+    // rounds carry forward even before anyone settles them
+    let allocation_limit = active_round
+        .allocation
+        .checked_add(ico.carried_into(active_round.round, current_time)?)
+        .ok_or(IcoError::MathOverflow)?
+        .checked_sub(active_round.tokens_sold)
+        .ok_or(IcoError::MathOverflow)?;
+    let vesting = ico.vesting_for(active_round.round);
+    let limits = ico.limits_for(active_round.round);
+    let round_bought = position.amount_for_mut(active_round.round);
+
+    require!(
+        amount <= allocation_limit,
+        IcoError::RoundAllocationExceeded
+    );
+
+    // Each purchase pays PurchaseAccount rent, so dust buys are rejected outright,
+    // while the wallet cap applies to everything bought in this round so far.
+    // Zero-amount buys would count the wallet as a new round buyer every time.
+    require!(amount > 0, IcoError::ZeroPurchaseAmount);
+    require!(
+        amount >= limits.min_purchase,
+        IcoError::BelowMinimumPurchase
+    );
+    let is_new_round_buyer = *round_bought == 0;
+    *round_bought = round_bought
+        .checked_add(amount)
+        .ok_or(IcoError::MathOverflow)?;
+    // The KYC tier scales the round's wallet cap; an uncapped round stays uncapped
+    let max_per_wallet = match kyc_tier {
+        Some(tier) if limits.max_per_wallet > 0 => {
+            let scaled = (limits.max_per_wallet as u128)
+                .checked_mul(ico.kyc_tier_wallet_bps[tier as usize] as u128)
+                .ok_or(IcoError::MathOverflow)?
+                / 10000;
+            Some(u64::try_from(scaled).unwrap_or(u64::MAX))
+        }
+        _ if limits.max_per_wallet > 0 => Some(limits.max_per_wallet),
+        _ => None,
+    };
+    if let Some(max_per_wallet) = max_per_wallet {
+        require!(
+            *round_bought <= max_per_wallet,
+            IcoError::WalletLimitExceeded
+        );
+    }
+    if let Some(investor_allocation) = investor_allocation {
+        require!(
+            *round_bought <= investor_allocation,
+            IcoError::InvestorAllocationExceeded
+        );
+    }
+
+    // A fresh position is the buyer's first purchase in this sale
+    let is_new_investor = position.buyer == Pubkey::default();
+    if is_new_investor {
+        position.buyer = buyer;
+        position.ico = ico_key;
+        position.bump = position_bump;
+    }
+
+    let remaining_supply = ico
+        .hard_cap()?
+        .checked_sub(ico.tokens_sold)
+        .ok_or(IcoError::MathOverflow)?;
+    require!(amount <= remaining_supply, IcoError::InsufficientTokens);
+
+    // Tokens already sold but not yet paid out are still owed from the vault
+    let outstanding = ico
+        .tokens_sold
+        .checked_sub(ico.tokens_distributed)
+        .ok_or(IcoError::MathOverflow)?;
+    let available = vault_balance
+        .checked_sub(outstanding)
+        .ok_or(IcoError::InsufficientVaultBalance)?;
+    require!(amount <= available, IcoError::InsufficientVaultBalance);
+
+    Ok(PurchaseTerms {
+        is_seed_round,
+        round: active_round.round,
+        round_price: active_round.price,
+        vesting,
+        is_new_investor,
+        is_new_round_buyer,
+        seed_price_numerator,
+        seed_price_denominator,
+    })
+}
+
+/// Writes the purchase record and updates the sale counters once payment has been taken.
+#[allow(clippy::too_many_arguments)]
+fn record_purchase(
+    ico: &mut IcoAccount,
+    position: &mut BuyerPosition,
+    purchase: &mut PurchaseAccount,
+    purchase_bump: u8,
+    ico_key: Pubkey,
+    buyer: Pubkey,
+    terms: &PurchaseTerms,
+    amount: u64,
+    price: u64,
+    total_cost: u64,
+    payment_mint: Pubkey,
+    current_time: i64,
+) -> Result<()> {
+    purchase.buyer = buyer;
+    purchase.amount = amount;
+    purchase.is_distributed = false;
+    purchase.timestamp = current_time;
+    purchase.ico = ico_key;
+    purchase.index = ico.purchase_counter;
+    purchase.bump = purchase_bump;
+    purchase.purchase_price = price;
+    purchase.total_cost = total_cost;
+    purchase.payment_mint = payment_mint;
+    purchase.round = terms.round;
+    purchase.claimed_amount = 0;
+    purchase.vesting = terms.vesting;
+
+    let schedule = ico.round_schedule_mut(terms.round)?;
+    schedule.tokens_sold = schedule
+        .tokens_sold
+        .checked_add(amount)
+        .ok_or(IcoError::MathOverflow)?;
+    if terms.is_new_round_buyer {
+        schedule.buyers = schedule
+            .buyers
+            .checked_add(1)
+            .ok_or(IcoError::MathOverflow)?;
+    }
+
+    ico.tokens_sold = ico
+        .tokens_sold
+        .checked_add(amount)
+        .ok_or(IcoError::MathOverflow)?;
+
+    // The soft cap compares sale value, so SOL and payment-mint buys count alike
+    let usd_value = (amount as u128)
+        .checked_mul(terms.usd_price()? as u128)
+        .ok_or(IcoError::MathOverflow)?
+        / 10u128
+            .checked_pow(ico.token_decimals as u32)
+            .ok_or(IcoError::MathOverflow)?;
+    let usd_value = u64::try_from(usd_value).map_err(|_| error!(IcoError::MathOverflow))?;
+    ico.usd_raised = ico
+        .usd_raised
+        .checked_add(usd_value)
+        .ok_or(IcoError::MathOverflow)?;
+
+    ico.purchase_counter = ico
+        .purchase_counter
+        .checked_add(1)
+        .ok_or(IcoError::MathOverflow)?;
+
+    if terms.is_new_investor {
+        ico.total_investors = ico
+            .total_investors
+            .checked_add(1)
+            .ok_or(IcoError::MathOverflow)?;
+    }
+
+    position.purchase_count = position
+        .purchase_count
+        .checked_add(1)
+        .ok_or(IcoError::MathOverflow)?;
+
+    emit!(TokenPurchaseEvent {
+        buyer,
+        amount,
+        price,
+        payment_mint,
+        round: terms.round,
+        timestamp: current_time,
+    });
+
+    if ico.sold_out_by(amount)? {
+        emit!(SaleSoldOutEvent {
+            tokens_sold: ico.tokens_sold,
+            total_raised: ico.total_raised,
+            timestamp: current_time,
+        });
+    }
+
+    Ok(())
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Copy)]
+pub enum Role {
+    Pauser,
+    WhitelistManager,
+    RoundOperator,
+    Distributor,
+    Treasurer,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Copy)]
+pub enum RoundType {
+    PreICO,
+    PublicICO,
+    Seed,
+    Private,
+}
+
+/// One round of the sale schedule, open from `start_time` until just before `end_time`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Copy)]
+pub struct RoundSchedule {
+    pub round: RoundType,         // 1
+    pub start_time: i64,          // 8
+    pub end_time: i64,            // 8
+    pub price: u64,               // 8, USD micro-units per whole token
+    pub allocation: u64,          // 8
+    pub tokens_sold: u64,         // 8
+    pub lamports_raised: u64,     // 8, SOL purchases only
+    pub buyers: u64,              // 8, distinct wallets that bought in this round
+    pub rollover: RolloverPolicy, // 1
+    pub is_settled: bool,         // 1
+}
+
+/// What happens to a round's unsold allocation once it ends.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Copy)]
+pub enum RolloverPolicy {
+    CarryForward,    // on sale in the next scheduled round as soon as this one ends
+    ReturnToReserve, // sent to the treasury's token account
+    Burn,
+}
+
+pub const MAX_ROUNDS: usize = 4;
+
+/// How a round's allocation is given to `initialize`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Copy)]
+pub enum RoundAllocation {
+    Bps(u16),    // share of total_supply
+    Amount(u64), // exact amount in base units
+}
+
+/// A round as passed to `initialize`, before its allocation is resolved against `total_supply`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Copy)]
+pub struct RoundConfig {
+    pub round: RoundType,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub price: u64,
+    pub allocation: RoundAllocation,
+    pub rollover: RolloverPolicy,
+}
+
+impl RoundConfig {
+    pub fn resolve(&self, total_supply: u64) -> Result<RoundSchedule> {
+        let allocation = match self.allocation {
+            RoundAllocation::Bps(bps) => {
+                require!(bps <= 10000, IcoError::AllocationExceedsSupply);
+                let amount = (total_supply as u128)
+                    .checked_mul(bps as u128)
+                    .ok_or(IcoError::MathOverflow)?
+                    / 10000;
+                u64::try_from(amount).map_err(|_| error!(IcoError::MathOverflow))?
+            }
+            RoundAllocation::Amount(amount) => amount,
+        };
+
+        Ok(RoundSchedule {
+            round: self.round,
+            start_time: self.start_time,
+            end_time: self.end_time,
+            price: self.price,
+            allocation,
+            tokens_sold: 0,
+            lamports_raised: 0,
+            buyers: 0,
+            rollover: self.rollover,
+            is_settled: false,
+        })
+    }
+}
+
+impl RoundSchedule {
+    /// Rounds must be listed in time order, without overlaps, and at most once each.
+    pub fn validate_schedule(rounds: &[RoundSchedule]) -> Result<()> {
+        require!(
+            !rounds.is_empty() && rounds.len() <= MAX_ROUNDS,
+            IcoError::InvalidRoundSchedule
+        );
+        for (i, schedule) in rounds.iter().enumerate() {
+            require!(
+                schedule.start_time < schedule.end_time,
+                IcoError::InvalidRoundSchedule
+            );
+            require!(schedule.price > 0, IcoError::InvalidPrice);
+            if let Some(previous) = i.checked_sub(1).map(|p| &rounds[p]) {
+                require!(
+                    schedule.start_time >= previous.end_time,
+                    IcoError::InvalidRoundSchedule
+                );
+            }
+            require!(
+                !rounds[..i].iter().any(|r| r.round == schedule.round),
+                IcoError::InvalidRoundSchedule
+            );
+        }
+        // The last round has nothing to carry its leftovers into
+        require!(
+            rounds.last().map(|r| r.rollover) != Some(RolloverPolicy::CarryForward),
+            IcoError::InvalidRoundSchedule
+        );
+        Ok(())
+    }
+}
+
+/// Fields left as `None` keep their current value.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct IcoParametersUpdate {
+    pub total_supply: Option<u64>,
+    pub seed_price: Option<u64>,
+    pub pre_ico_price: Option<u64>,
+    pub public_price: Option<u64>,
+    pub seed_round_allocation: Option<u64>,
+    pub private_price: Option<u64>,
+    pub private_allocation: Option<u64>,
+    pub pre_ico_allocation: Option<u64>,
+    pub public_round_allocation: Option<u64>,
+    /// Sale length from `start_time`; moves the end of the last round.
+    pub duration: Option<i64>,
+}
+
+/// Sensitive changes that require council approval once a council is configured.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub enum ProposalAction {
+    UpdateIcoParameters(IcoParametersUpdate),
+    UpdateRound {
+        round: RoundType,
+        start_time: i64,
+        end_time: i64,
+    },
+    SetTreasuryWallet(Pubkey),
+    GrantRole {
+        role: Role,
+        account: Pubkey,
+    },
+    RevokeRole(Role),
+    /// Seed, private, pre-ICO and public schedules, in that order.
+    ConfigureVesting([VestingSchedule; 4]),
+    /// Seed, private, pre-ICO and public limits, in that order.
+    ConfigurePurchaseLimits([PurchaseLimits; 4]),
+    ConfigureKyc {
+        kyc_signer: Pubkey,
+        tier_wallet_bps: [u16; KYC_TIER_COUNT],
+    },
+    UpdateCouncil {
+        members: Vec<Pubkey>,
+        threshold: u8,
+        timelock: i64,
+    },
+    ConfigureSeedWhitelist([u8; 32]),
+    /// Executing it creates the investor's `SeedAllocation`.
+    AddSeedInvestor {
+        investor: Pubkey,
+        allocation: u64,
+        price: u64,
+    },
+    /// Seed, private, pre-ICO and public prices, in that order. Executing it
+    /// creates the mint's payment vault.
+    AddPaymentMint {
+        mint: Pubkey,
+        prices: [u64; 4],
+    },
+    ConfigurePriceOracle {
+        price_oracle: Pubkey,
+        oracle_program: Pubkey,
+        max_price_staleness: i64,
+        max_confidence_bps: u16,
+    },
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Copy)]
+pub enum IcoParameter {
+    TotalSupply,
+    SeedPrice,
+    PreIcoPrice,
+    PublicPrice,
+    StartTime,
+    Duration,
+    SeedRoundAllocation,
+    PreIcoAllocation,
+    PublicRoundAllocation,
+    PrivatePrice,
+    PrivateAllocation,
+}
+
+#[event]
+pub struct IcoParameterUpdatedEvent {
+    pub parameter: IcoParameter,
+    pub old_value: u64,
+    pub new_value: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CouncilConfiguredEvent {
+    pub council: Pubkey,
+    pub members: Vec<Pubkey>,
+    pub threshold: u8,
+    pub timelock: i64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProposalCreatedEvent {
+    pub proposal: Pubkey,
+    pub index: u64,
+    pub proposer: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProposalApprovedEvent {
+    pub proposal: Pubkey,
+    pub member: Pubkey,
+    pub approvals: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ProposalExecutedEvent {
+    pub proposal: Pubkey,
+    pub executor: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TreasuryWalletUpdatedEvent {
+    pub previous_wallet: Pubkey,
+    pub new_wallet: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AuthorityProposedEvent {
+    pub authority: Pubkey,
+    pub pending_authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AuthorityTransferCancelledEvent {
+    pub authority: Pubkey,
+    pub cancelled_authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AuthorityTransferredEvent {
+    pub previous_authority: Pubkey,
+    pub new_authority: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RoleGrantedEvent {
+    pub role: Role,
+    pub account: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RoleRevokedEvent {
+    pub role: Role,
+    pub account: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PauseEvent {
+    pub paused: bool,
+    pub by: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TokenPurchaseEvent {
+    pub buyer: Pubkey,
+    pub amount: u64,
+    pub price: u64,
+    pub payment_mint: Pubkey,
+    pub round: RoundType,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct TokenDistributionEvent {
+    pub buyer: Pubkey,
+    pub amount: u64,
+    pub round: RoundType,
+}
+
+#[event]
+pub struct BatchDistributionEvent {
+    pub distributed_count: u64,
+    pub skipped_count: u64,
+    pub total_amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VestedTokensClaimedEvent {
+    pub buyer: Pubkey,
+    pub amount: u64,
+    pub total_claimed: u64,
+    pub round: RoundType,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct VestingConfiguredEvent {
+    pub seed_vesting: VestingSchedule,
+    pub private_vesting: VestingSchedule,
+    pub pre_ico_vesting: VestingSchedule,
+    pub public_vesting: VestingSchedule,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PurchaseLimitsConfiguredEvent {
+    pub seed_limits: PurchaseLimits,
+    pub private_limits: PurchaseLimits,
+    pub pre_ico_limits: PurchaseLimits,
+    pub public_limits: PurchaseLimits,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct KycConfiguredEvent {
+    pub kyc_signer: Pubkey,
+    pub tier_wallet_bps: [u16; KYC_TIER_COUNT],
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PriceOracleConfiguredEvent {
+    pub price_oracle: Pubkey,
+    pub oracle_program: Pubkey,
+    pub max_price_staleness: i64,
+    pub max_confidence_bps: u16,
+}
+
+#[event]
+pub struct SeedInvestorAddedEvent {
+    pub investor: Pubkey,
+    pub allocation: u64,
+    pub price: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SeedInvestorRemovedEvent {
+    pub investor: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SeedWhitelistConfiguredEvent {
+    pub merkle_root: [u8; 32],
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PaymentMintAddedEvent {
+    pub mint: Pubkey,
+    pub treasury_token_account: Pubkey,
+    pub seed_price: u64,
+    pub private_price: u64,
+    pub pre_ico_price: u64,
+    pub public_price: u64,
+}
+
+#[event]
+pub struct PaymentTokensWithdrawnEvent {
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub destination: Pubkey,
+}
+
+#[event]
+pub struct SaleTokensDepositedEvent {
+    pub depositor: Pubkey,
+    pub amount: u64,
+    pub vault_balance: u64,
+}
+
+#[event]
+pub struct SaleTokensWithdrawnEvent {
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub vault_balance: u64,
+}
+
+#[event]
+pub struct SaleSoldOutEvent {
+    pub tokens_sold: u64,
+    pub total_raised: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct IcoEndedEvent {
+    pub total_raised: u64,
+    pub usd_raised: u64,
+    pub soft_cap: u64,
+    pub soft_cap_reached: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RoundRolloverEvent {
+    pub round: RoundType,
+    pub policy: RolloverPolicy,
+    pub unsold: u64,
+    pub amount_moved: u64,
+    pub destination: Option<RoundType>, // set when carried forward
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RefundEvent {
+    pub buyer: Pubkey,
+    pub amount: u64,
+    pub tokens: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RoundUpdateEvent {
+    pub round: RoundType,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub timestamp: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Copy)]
+pub struct VestingSchedule {
+    pub tge_unlock_bps: u16,   // 2
+    pub cliff_duration: i64,   // 8
+    pub vesting_duration: i64, // 8
+}
+
+impl VestingSchedule {
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.tge_unlock_bps <= 10000 && self.cliff_duration >= 0 && self.vesting_duration >= 0,
+            IcoError::InvalidVestingSchedule
+        );
+        Ok(())
+    }
+}
+
+pub const MAX_PAYMENT_MINTS: usize = 5;
+
+/// An accepted SPL payment token, priced per round in its own base units per
+/// whole sale token.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Default)]
+pub struct PaymentMint {
+    pub mint: Pubkey,                   // 32
+    pub treasury_token_account: Pubkey, // 32
+    pub seed_price: u64,                // 8
+    pub private_price: u64,             // 8
+    pub pre_ico_price: u64,             // 8
+    pub public_price: u64,              // 8
+    pub total_raised: u64,              // 8
+}
+
+impl PaymentMint {
+    pub fn price_for(&self, round: RoundType) -> u64 {
+        match round {
+            RoundType::Seed => self.seed_price,
+            RoundType::Private => self.private_price,
+            RoundType::PreICO => self.pre_ico_price,
+            RoundType::PublicICO => self.public_price,
+        }
+    }
+
+    /// Payment base units owed for `amount` sale base units at `price` per whole
+    /// token, rounded up in the sale's favour.
+    pub fn cost_for(amount: u64, price: u64, token_decimals: u8) -> Result<u64> {
+        let denominator = 10u128
+            .checked_pow(token_decimals as u32)
+            .ok_or(IcoError::MathOverflow)?;
+        let cost = (amount as u128)
+            .checked_mul(price as u128)
+            .ok_or(IcoError::MathOverflow)?
+            .checked_add(denominator - 1)
+            .ok_or(IcoError::MathOverflow)?
+            / denominator;
+        u64::try_from(cost).map_err(|_| error!(IcoError::MathOverflow))
+    }
+}
+
+/// Per-round wallet limits; a `max_per_wallet` of 0 leaves wallets uncapped.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, PartialEq, Copy, Default)]
+pub struct PurchaseLimits {
+    pub min_purchase: u64,   // 8
+    pub max_per_wallet: u64, // 8
+}
+
+impl PurchaseLimits {
+    pub fn validate(&self) -> Result<()> {
+        require!(
+            self.max_per_wallet == 0 || self.min_purchase <= self.max_per_wallet,
+            IcoError::InvalidPurchaseLimits
+        );
+        Ok(())
+    }
+}
+
+#[account]
+#[derive(Default)]
+pub struct IcoAccount {
+    pub authority: Pubkey,                          // 32
+    pub pending_authority: Pubkey,                  // 32
+    pub pauser: Pubkey,                             // 32
+    pub whitelist_manager: Pubkey,                  // 32
+    pub round_operator: Pubkey,                     // 32
+    pub distributor: Pubkey,                        // 32
+    pub treasurer: Pubkey,                          // 32
+    pub token_mint: Pubkey,                         // 32
+    pub token_vault: Pubkey,                        // 32
+    pub token_decimals: u8,                         // 1
+    pub treasury_wallet: Pubkey,                    // 32
+    pub total_supply: u64,                          // 8
+    pub tokens_sold: u64,                           // 8
+    pub tokens_distributed: u64,                    // 8
+    pub start_time: i64,                            // 8
+    pub duration: i64,                              // 8
+    pub is_active: bool,                            // 1
+    pub is_paused: bool,                            // 1
+    pub soft_cap: u64,                              // 8, USD micro-units, compared with usd_raised
+    pub total_raised: u64,                          // 8, lamports in escrow
+    pub usd_raised: u64,                            // 8, USD micro-units, SOL and payment mints
+    pub is_refunding: bool,                         // 1
+    pub seed_merkle_root: [u8; 32],                 // 32, all zeroes when unpublished
+    pub total_investors: u64,                       // 8
+    pub purchase_counter: u64,                      // 8
+    pub rounds: Vec<RoundSchedule>,                 // 4 + (59 * MAX_ROUNDS)
+    pub seed_vesting: VestingSchedule,              // 18
+    pub private_vesting: VestingSchedule,           // 18
+    pub pre_ico_vesting: VestingSchedule,           // 18
+    pub public_vesting: VestingSchedule,            // 18
+    pub seed_limits: PurchaseLimits,                // 16
+    pub private_limits: PurchaseLimits,             // 16
+    pub pre_ico_limits: PurchaseLimits,             // 16
+    pub public_limits: PurchaseLimits,              // 16
+    pub payment_mints: Vec<PaymentMint>,            // 4 + (104 * MAX_PAYMENT_MINTS)
+    pub price_oracle: Pubkey,                       // 32
+    pub oracle_program: Pubkey,                     // 32, owner the price feed must have
+    pub max_price_staleness: i64,                   // 8
+    pub max_confidence_bps: u16,                    // 2
+    pub council: Pubkey,                            // 32
+    pub kyc_signer: Pubkey,                         // 32
+    pub kyc_tier_wallet_bps: [u16; KYC_TIER_COUNT], // 2 * KYC_TIER_COUNT
+}
+
+pub const MAX_COUNCIL_MEMBERS: usize = 10;
+
+#[account]
+pub struct Council {
+    pub ico: Pubkey,          // 32
+    pub members: Vec<Pubkey>, // 4 + (32 * MAX_COUNCIL_MEMBERS)
+    pub threshold: u8,        // 1
+    pub timelock: i64,        // 8, seconds between reaching the threshold and execution
+    pub proposal_count: u64,  // 8
+    pub bump: u8,             // 1
+}
+
+impl Council {
+    pub fn validate_config(members: &[Pubkey], threshold: u8, timelock: i64) -> Result<()> {
+        require!(
+            !members.is_empty() && members.len() <= MAX_COUNCIL_MEMBERS,
+            IcoError::InvalidCouncilConfig
+        );
+        require!(
+            threshold > 0 && threshold as usize <= members.len(),
+            IcoError::InvalidCouncilConfig
+        );
+        require!(timelock >= 0, IcoError::InvalidCouncilConfig);
+        for (i, member) in members.iter().enumerate() {
+            require!(
+                *member != Pubkey::default() && !members[..i].contains(member),
+                IcoError::InvalidCouncilConfig
+            );
+        }
+        Ok(())
+    }
+
+    pub fn is_member(&self, key: &Pubkey) -> bool {
+        self.members.contains(key)
+    }
+
+    pub fn count_approvals(&self, approvals: &[Pubkey]) -> u8 {
+        approvals.iter().filter(|key| self.is_member(key)).count() as u8
+    }
+}
+
+#[account]
+pub struct Proposal {
+    pub council: Pubkey,        // 32
+    pub index: u64,             // 8
+    pub proposer: Pubkey,       // 32
+    pub action: ProposalAction, // 1 + 333, sized for the largest variant (UpdateCouncil)
+    pub approvals: Vec<Pubkey>, // 4 + (32 * MAX_COUNCIL_MEMBERS)
+    pub created_at: i64,        // 8
+    pub approved_at: i64,       // 8, 0 until the threshold is reached
+    pub executed: bool,         // 1
+    pub bump: u8,               // 1
+}
+
+impl Proposal {
+    /// Records `member`'s approval and returns the approvals that still count. The
+    /// timelock starts whenever the threshold is reached, including again after a
+    /// council update had dropped the proposal below it.
+    pub fn add_approval(&mut self, council: &Council, member: Pubkey, now: i64) -> u8 {
+        let was_approved =
+            self.approved_at != 0 && council.count_approvals(&self.approvals) >= council.threshold;
+        self.approvals.push(member);
+
+        let approvals = council.count_approvals(&self.approvals);
+        if !was_approved && approvals >= council.threshold {
+            self.approved_at = now;
+        }
+        approvals
+    }
+
+    /// Investor whose seed allocation executing this proposal creates, if any.
+    pub fn seed_investor(&self) -> Pubkey {
+        match self.action {
+            ProposalAction::AddSeedInvestor { investor, .. } => investor,
+            _ => Pubkey::default(),
+        }
+    }
+
+    /// Mint whose payment vault executing this proposal creates, if any.
+    pub fn payment_mint(&self) -> Pubkey {
+        match self.action {
+            ProposalAction::AddPaymentMint { mint, .. } => mint,
+            _ => Pubkey::default(),
+        }
+    }
+}
+
+/// An individually negotiated seed deal.
+#[account]
+pub struct SeedAllocation {
+    pub ico: Pubkey,      // 32
+    pub investor: Pubkey, // 32
+    pub allocation: u64,  // 8, checked against BuyerPosition::seed_amount
+    pub price: u64,       // 8, USD micro-units per whole token
+    pub bump: u8,         // 1
+}
+
+#[account]
+pub struct BuyerPosition {
+    pub buyer: Pubkey,       // 32
+    pub ico: Pubkey,         // 32
+    pub seed_amount: u64,    // 8
+    pub private_amount: u64, // 8
+    pub pre_ico_amount: u64, // 8
+    pub public_amount: u64,  // 8
+    pub lamports_paid: u64,  // 8
+    pub purchase_count: u64, // 8
+    pub bump: u8,            // 1
+}
+
+impl BuyerPosition {
+    /// Running total bought in `round`, which the wallet cap applies to.
+    pub fn amount_for_mut(&mut self, round: RoundType) -> &mut u64 {
+        match round {
+            RoundType::Seed => &mut self.seed_amount,
+            RoundType::Private => &mut self.private_amount,
+            RoundType::PreICO => &mut self.pre_ico_amount,
+            RoundType::PublicICO => &mut self.public_amount,
+        }
+    }
+}
+
+impl IcoAccount {
+    /// The authority holds every role; other keys only the role they were granted.
+    pub fn has_role(&self, role: Role, key: &Pubkey) -> bool {
+        *key == self.authority || *key == self.role_holder(role)
+    }
+
+    pub fn role_holder(&self, role: Role) -> Pubkey {
+        match role {
+            Role::Pauser => self.pauser,
+            Role::WhitelistManager => self.whitelist_manager,
+            Role::RoundOperator => self.round_operator,
+            Role::Distributor => self.distributor,
+            Role::Treasurer => self.treasurer,
+        }
+    }
+
+    fn role_holder_mut(&mut self, role: Role) -> &mut Pubkey {
+        match role {
+            Role::Pauser => &mut self.pauser,
+            Role::WhitelistManager => &mut self.whitelist_manager,
+            Role::RoundOperator => &mut self.round_operator,
+            Role::Distributor => &mut self.distributor,
+            Role::Treasurer => &mut self.treasurer,
+        }
+    }
+
+    /// Sale tokens the vault has to keep: sold but not yet paid out, plus what
+    /// the rounds still have on offer. A refunding sale offers nothing more.
+    pub fn reserved_sale_tokens(&self) -> Result<u64> {
+        let outstanding = self
+            .tokens_sold
+            .checked_sub(self.tokens_distributed)
+            .ok_or(IcoError::MathOverflow)?;
+        if self.is_refunding {
+            return Ok(outstanding);
+        }
+        self.rounds
+            .iter()
+            .try_fold(outstanding, |total, r| {
+                total.checked_add(r.allocation.saturating_sub(r.tokens_sold))
+            })
+            .ok_or(error!(IcoError::MathOverflow))
+    }
+
+    /// Takes a refunded purchase off the sale-wide and round sold counters, so its
+    /// tokens stop being held for the buyer.
+    pub fn release_refunded(&mut self, purchase: &PurchaseAccount) -> Result<()> {
+        self.tokens_sold = self
+            .tokens_sold
+            .checked_sub(purchase.amount)
+            .ok_or(IcoError::MathOverflow)?;
+        let schedule = self.round_schedule_mut(purchase.round)?;
+        schedule.tokens_sold = schedule
+            .tokens_sold
+            .checked_sub(purchase.amount)
+            .ok_or(IcoError::MathOverflow)?;
+        Ok(())
+    }
+
+    /// Every scheduled round's allocation combined.
+    pub fn allocated_supply(&self) -> Result<u64> {
+        self.rounds
+            .iter()
+            .try_fold(0u64, |total, r| total.checked_add(r.allocation))
+            .ok_or(error!(IcoError::MathOverflow))
+    }
+
+    /// Sale-wide cap on tokens sold: the round allocations combined, never more than `total_supply`.
+    pub fn hard_cap(&self) -> Result<u64> {
+        Ok(self.allocated_supply()?.min(self.total_supply))
+    }
+
+    /// Unsold tokens that ended, unsettled earlier rounds carry forward into
+    /// `round`. `settle_round` books them later; sales can use them right away.
+    pub fn carried_into(&self, round: RoundType, now: i64) -> Result<u64> {
+        let index = self
+            .rounds
+            .iter()
+            .position(|r| r.round == round)
+            .ok_or(IcoError::RoundNotScheduled)?;
+
+        let mut carried: u64 = 0;
+        for earlier in self.rounds[..index].iter().rev() {
+            if earlier.is_settled
+                || earlier.rollover != RolloverPolicy::CarryForward
+                || earlier.end_time > now
+            {
+                break;
+            }
+            carried = carried
+                .checked_add(earlier.allocation.saturating_sub(earlier.tokens_sold))
+                .ok_or(IcoError::MathOverflow)?;
+        }
+        Ok(carried)
+    }
+
+    /// Whether a purchase of `amount`, already added to `tokens_sold`, is the one
+    /// that reached the hard cap.
+    pub fn sold_out_by(&self, amount: u64) -> Result<bool> {
+        let hard_cap = self.hard_cap()?;
+        let sold_before = self
+            .tokens_sold
+            .checked_sub(amount)
+            .ok_or(IcoError::MathOverflow)?;
+        Ok(self.tokens_sold >= hard_cap && sold_before < hard_cap)
+    }
+
+    /// The round whose window contains `now`, if any.
+    pub fn active_round(&self, now: i64) -> Option<&RoundSchedule> {
+        self.rounds
+            .iter()
+            .find(|r| now >= r.start_time && now < r.end_time)
+    }
+
+    pub fn round_schedule(&self, round: RoundType) -> Result<&RoundSchedule> {
+        self.rounds
+            .iter()
+            .find(|r| r.round == round)
+            .ok_or(error!(IcoError::RoundNotScheduled))
+    }
+
+    /// Ends the sale and reports whether the soft cap was met. SOL and payment-mint
+    /// purchases both count, valued at the USD price of the round they were made in;
+    /// on a miss each is refunded from where it was paid in.
+    pub fn close_sale(&mut self) -> bool {
+        self.is_active = false;
+        let soft_cap_reached = self.usd_raised >= self.soft_cap;
+        self.is_refunding = !soft_cap_reached;
+        soft_cap_reached
+    }
+
+    /// Payment-mint vaults stay locked until the sale has closed above its soft cap.
+    pub fn proceeds_withdrawable(&self) -> bool {
+        !self.is_active && !self.is_refunding
+    }
+
+    /// End of the last round; cliffs and linear unlocks are measured from here.
+    pub fn sale_end(&self) -> Result<i64> {
+        Ok(self
+            .start_time
+            .checked_add(self.duration)
+            .ok_or(IcoError::MathOverflow)?)
+    }
+
+    pub fn vesting_for(&self, round: RoundType) -> VestingSchedule {
+        match round {
+            RoundType::Seed => self.seed_vesting,
+            RoundType::Private => self.private_vesting,
+            RoundType::PreICO => self.pre_ico_vesting,
+            RoundType::PublicICO => self.public_vesting,
+        }
+    }
+
+    pub fn limits_for(&self, round: RoundType) -> PurchaseLimits {
+        match round {
+            RoundType::Seed => self.seed_limits,
+            RoundType::Private => self.private_limits,
+            RoundType::PreICO => self.pre_ico_limits,
+            RoundType::PublicICO => self.public_limits,
+        }
+    }
+
+    pub fn round_schedule_mut(&mut self, round: RoundType) -> Result<&mut RoundSchedule> {
+        self.rounds
+            .iter_mut()
+            .find(|r| r.round == round)
+            .ok_or(error!(IcoError::RoundNotScheduled))
+    }
+
+    /// Keeps `start_time` and `duration` spanning the first to the last scheduled round.
+    fn sync_sale_window(&mut self) -> Result<()> {
+        let first = self.rounds.first().ok_or(IcoError::InvalidRoundSchedule)?;
+        let last = self.rounds.last().ok_or(IcoError::InvalidRoundSchedule)?;
+        self.start_time = first.start_time;
+        self.duration = last
+            .end_time
+            .checked_sub(first.start_time)
+            .ok_or(IcoError::MathOverflow)?;
+        Ok(())
+    }
+}
+
+#[account]
+#[derive(Default)]
+pub struct PurchaseAccount {
+    pub buyer: Pubkey,            // 32
+    pub amount: u64,              // 8
+    pub is_distributed: bool,     // 1
+    pub timestamp: i64,           // 8
+    pub ico: Pubkey,              // 32
+    pub index: u64,               // 8, ico.purchase_counter at purchase time
+    pub bump: u8,                 // 1
+    pub purchase_price: u64,      // 8, USD micro-units for SOL purchases
+    pub total_cost: u64,          // 8
+    pub payment_mint: Pubkey,     // 32 (default for SOL purchases)
+    pub round: RoundType,         // 1
+    pub claimed_amount: u64,      // 8
+    pub vesting: VestingSchedule, // 18
+}
+
+impl PurchaseAccount {
+    /// Tokens unlocked at `now`: the TGE share right away, the rest linearly
+    /// once the cliff after `vesting_start` has passed. `vesting_start` is the
+    /// sale end read at claim time, so round updates after purchase still apply.
+    pub fn vested_amount(&self, vesting_start: i64, now: i64) -> Result<u64> {
+        let tge_amount = (self.amount as u128)
+            .checked_mul(self.vesting.tge_unlock_bps as u128)
+            .ok_or(IcoError::MathOverflow)?
+            .checked_div(10000)
+            .ok_or(IcoError::MathOverflow)? as u64;
+
+        let cliff_end = vesting_start
+            .checked_add(self.vesting.cliff_duration)
+            .ok_or(IcoError::MathOverflow)?;
+        if now < cliff_end {
+            return Ok(tge_amount);
+        }
+        if self.vesting.vesting_duration == 0 {
+            return Ok(self.amount);
+        }
+
+        let elapsed = now
+            .checked_sub(cliff_end)
+            .ok_or(IcoError::MathOverflow)?
+            .min(self.vesting.vesting_duration);
+        let linear_amount = (self.amount - tge_amount) as u128 * elapsed as u128
+            / self.vesting.vesting_duration as u128;
+
+        Ok(tge_amount
+            .checked_add(linear_amount as u64)
+            .ok_or(IcoError::MathOverflow)?)
+    }
+}
+
+#[derive(Accounts)]
+pub struct UpdateRound<'info> {
+    #[account(mut)]
+    pub round_operator: Signer<'info>,
+
+    #[account(mut, seeds = [b"ico", ico_account.token_mint.as_ref()], bump)]
+    pub ico_account: Account<'info, IcoAccount>,
+}
+
+#[derive(Accounts)]
+pub struct ConfigureVesting<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(mut, seeds = [b"ico", ico_account.token_mint.as_ref()], bump, has_one = authority)]
+    pub ico_account: Account<'info, IcoAccount>,
+}
+
+#[derive(Accounts)]
+pub struct ConfigurePriceOracle<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(mut, seeds = [b"ico", ico_account.token_mint.as_ref()], bump, has_one = authority)]
+    pub ico_account: Account<'info, IcoAccount>,
+
+    /// CHECK: Owner checked and parsed as a price feed in the instruction
+    pub price_oracle: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ConfigureKyc<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(mut, seeds = [b"ico", ico_account.token_mint.as_ref()], bump, has_one = authority)]
+    pub ico_account: Account<'info, IcoAccount>,
+}
+
+#[derive(Accounts)]
+pub struct ConfigurePurchaseLimits<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(mut, seeds = [b"ico", ico_account.token_mint.as_ref()], bump, has_one = authority)]
+    pub ico_account: Account<'info, IcoAccount>,
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub token_mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 +    // discriminator
+            32 +       // authority
+            32 +       // pending_authority
+            32 +       // pauser
+            32 +       // whitelist_manager
+            32 +       // round_operator
+            32 +       // distributor
+            32 +       // treasurer
+            32 +       // token_mint
+            32 +       // token_vault
+            1 +        // token_decimals
+            32 +       // treasury_wallet
+            8 +        // total_supply
+            8 +        // tokens_sold
+            8 +        // tokens_distributed
+            8 +        // start_time
+            8 +        // duration
+            1 +        // is_active
+            1 +        // is_paused
+            8 +        // soft_cap
+            8 +        // total_raised
+            8 +        // usd_raised
+            1 +        // is_refunding
+            32 +       // seed_merkle_root
+            8 +        // total_investors
+            8 +        // purchase_counter
+            4 + (59 * MAX_ROUNDS) + // rounds
+            18 * 4 +   // seed, private, pre_ico and public vesting
+            16 * 4 +   // seed, private, pre_ico and public limits
+            4 + (104 * MAX_PAYMENT_MINTS) + // payment_mints
+            32 +       // price_oracle
+            32 +       // oracle_program
+            8 +        // max_price_staleness
+            2 +        // max_confidence_bps
+            32 +       // council
+            32 +       // kyc_signer
+            2 * KYC_TIER_COUNT, // kyc_tier_wallet_bps
+        seeds = [b"ico", token_mint.key().as_ref()],
+        bump
+    )]
+    pub ico_account: Account<'info, IcoAccount>,
+
+    #[account(
+        init,
+        payer = authority,
+        token::mint = token_mint,
+        token::authority = ico_account,
+        seeds = [b"vault", ico_account.key().as_ref()],
+        bump
+    )]
+    pub token_vault: Account<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"escrow", ico_account.key().as_ref()], bump)]
+    pub escrow: SystemAccount<'info>,
+
+    /// CHECK: Only stored as the destination for raised SOL once the soft cap is met
+    pub treasury_wallet: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, token::Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateIcoParameters<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"ico", ico_account.token_mint.as_ref()],
+        bump,
+        has_one = authority
+    )]
+    pub ico_account: Account<'info, IcoAccount>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeAuthority<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"ico", ico_account.token_mint.as_ref()],
+        bump,
+        has_one = authority
+    )]
+    pub ico_account: Account<'info, IcoAccount>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+    pub pending_authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"ico", ico_account.token_mint.as_ref()],
+        bump,
+        has_one = pending_authority @ IcoError::InvalidPendingAuthority
+    )]
+    pub ico_account: Account<'info, IcoAccount>,
+}
+
+#[derive(Accounts)]
+pub struct ManageRole<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"ico", ico_account.token_mint.as_ref()],
+        bump,
+        has_one = authority
+    )]
+    pub ico_account: Account<'info, IcoAccount>,
+}
+
+#[derive(Accounts)]
+pub struct ConfigureCouncil<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"ico", ico_account.token_mint.as_ref()],
+        bump,
+        has_one = authority
+    )]
+    pub ico_account: Account<'info, IcoAccount>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + 32 + 4 + (32 * MAX_COUNCIL_MEMBERS) + 1 + 8 + 8 + 1,
+        seeds = [b"council", ico_account.key().as_ref()],
+        bump
+    )]
+    pub council: Account<'info, Council>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CreateProposal<'info> {
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"council", council.ico.as_ref()],
+        bump = council.bump
+    )]
+    pub council: Account<'info, Council>,
+
+    #[account(
+        init,
+        payer = proposer,
+        space = 8 +    // discriminator
+            32 +       // council
+            8 +        // index
+            32 +       // proposer
+            1 + 4 + (32 * MAX_COUNCIL_MEMBERS) + 1 + 8 + // action
+            4 + (32 * MAX_COUNCIL_MEMBERS) + // approvals
+            8 +        // created_at
+            8 +        // approved_at
+            1 +        // executed
+            1,         // bump
+        seeds = [
+            b"proposal",
+            council.key().as_ref(),
+            &council.proposal_count.to_le_bytes()
+        ],
+        bump
+    )]
+    pub proposal: Account<'info, Proposal>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveProposal<'info> {
+    pub member: Signer<'info>,
+
+    #[account(
+        seeds = [b"council", council.ico.as_ref()],
+        bump = council.bump
+    )]
+    pub council: Account<'info, Council>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"proposal",
+            council.key().as_ref(),
+            &proposal.index.to_le_bytes()
+        ],
+        bump = proposal.bump,
+        has_one = council
+    )]
+    pub proposal: Account<'info, Proposal>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteProposal<'info> {
+    #[account(mut)]
+    pub executor: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"ico", ico_account.token_mint.as_ref()],
+        bump,
+        has_one = council
+    )]
+    pub ico_account: Account<'info, IcoAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"council", ico_account.key().as_ref()],
+        bump = council.bump
+    )]
+    pub council: Account<'info, Council>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"proposal",
+            council.key().as_ref(),
+            &proposal.index.to_le_bytes()
+        ],
+        bump = proposal.bump,
+        has_one = council
+    )]
+    pub proposal: Account<'info, Proposal>,
+
+    /// Only needed for `AddSeedInvestor`.
+    #[account(
+        init,
+        payer = executor,
+        space = 8 + 32 + 32 + 8 + 8 + 1, // discriminator + fields
+        seeds = [b"seed", ico_account.key().as_ref(), proposal.seed_investor().as_ref()],
+        bump
+    )]
+    pub seed_allocation: Option<Account<'info, SeedAllocation>>,
+
+    /// Only needed for `AddPaymentMint`.
+    pub payment_mint: Option<Account<'info, Mint>>,
+
+    #[account(
+        init,
+        payer = executor,
+        token::mint = payment_mint,
+        token::authority = ico_account,
+        seeds = [b"payment_vault", ico_account.key().as_ref(), proposal.payment_mint().as_ref()],
+        bump
+    )]
+    pub payment_vault: Option<Account<'info, TokenAccount>>,
+
+    /// Only needed for `ConfigurePriceOracle`.
+    /// CHECK: Checked against the proposal, then parsed as a price feed in the instruction
+    pub price_oracle: Option<UncheckedAccount<'info>>,
+
+    pub token_program: Option<Program<'info, token::Token>>,
+    pub system_program: Option<Program<'info, System>>,
+}
+
+#[derive(Accounts)]
+pub struct SetPaused<'info> {
+    pub pauser: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"ico", ico_account.token_mint.as_ref()],
+        bump,
+        constraint = ico_account.has_role(Role::Pauser, &pauser.key()) @ IcoError::Unauthorized
+    )]
+    pub ico_account: Account<'info, IcoAccount>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawSaleTokenSurplus<'info> {
+    pub treasurer: Signer<'info>,
+
+    #[account(
+        seeds = [b"ico", ico_account.token_mint.as_ref()],
+        bump,
+        has_one = token_vault,
+        constraint = ico_account.has_role(Role::Treasurer, &treasurer.key()) @ IcoError::Unauthorized
+    )]
+    pub ico_account: Account<'info, IcoAccount>,
+
+    #[account(mut)]
+    pub token_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = treasury_token_account.owner == ico_account.treasury_wallet @ IcoError::InvalidTokenAccount,
+        constraint = treasury_token_account.mint == ico_account.token_mint @ IcoError::InvalidTokenMint
+    )]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, token::Token>,
+}
+
+#[derive(Accounts)]
+pub struct DepositSaleTokens<'info> {
+    pub treasurer: Signer<'info>,
+
+    #[account(
+        seeds = [b"ico", ico_account.token_mint.as_ref()],
+        bump,
+        has_one = token_vault,
+        constraint = ico_account.has_role(Role::Treasurer, &treasurer.key()) @ IcoError::Unauthorized
+    )]
+    pub ico_account: Account<'info, IcoAccount>,
+
+    #[account(mut)]
+    pub token_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = treasurer_token_account.mint == ico_account.token_mint @ IcoError::InvalidTokenMint
+    )]
+    pub treasurer_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, token::Token>,
+}
+
+#[derive(Accounts)]
+pub struct BuyTokens<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    #[account(mut, seeds = [b"ico", ico_account.token_mint.as_ref()], bump)]
+    pub ico_account: Account<'info, IcoAccount>,
+
+    #[account(
+        init,
+        payer = buyer,
+        space = 8 + 32 + 8 + 1 + 8 + 32 + 8 + 1 + 8 + 8 + 32 + 1 + 8 + 18, // discriminator + fields
+        seeds = [
+            b"purchase",
+            ico_account.key().as_ref(),
+            buyer.key().as_ref(),
+            &ico_account.purchase_counter.to_le_bytes()
+        ],
+        bump
+    )]
+    pub purchase_account: Account<'info, PurchaseAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = 8 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 1, // discriminator + fields
+        seeds = [b"position", ico_account.key().as_ref(), buyer.key().as_ref()],
+        bump
+    )]
+    pub buyer_position: Account<'info, BuyerPosition>,
+
+    #[account(
+        seeds = [b"seed", ico_account.key().as_ref(), buyer.key().as_ref()],
+        bump = seed_allocation.bump
+    )]
+    pub seed_allocation: Option<Account<'info, SeedAllocation>>,
+
+    #[account(address = ico_account.token_vault @ IcoError::InvalidTokenAccount)]
+    pub token_vault: Account<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"escrow", ico_account.key().as_ref()], bump)]
+    pub escrow: SystemAccount<'info>,
+
+    #[account(
+        address = ico_account.price_oracle @ IcoError::InvalidOracle,
+        owner = ico_account.oracle_program @ IcoError::InvalidOracle
+    )]
+    /// CHECK: Parsed and validated as a price feed in the instruction
+    pub price_oracle: UncheckedAccount<'info>,
+
+    /// CHECK: Instructions sysvar, read to find the KYC attestation
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, token::Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BuyTokensWithSpl<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    #[account(mut, seeds = [b"ico", ico_account.token_mint.as_ref()], bump)]
+    pub ico_account: Account<'info, IcoAccount>,
+
+    #[account(
+        init,
+        payer = buyer,
+        space = 8 + 32 + 8 + 1 + 8 + 32 + 8 + 1 + 8 + 8 + 32 + 1 + 8 + 18, // discriminator + fields
+        seeds = [
+            b"purchase",
+            ico_account.key().as_ref(),
+            buyer.key().as_ref(),
+            &ico_account.purchase_counter.to_le_bytes()
+        ],
+        bump
+    )]
+    pub purchase_account: Account<'info, PurchaseAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = 8 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 1, // discriminator + fields
+        seeds = [b"position", ico_account.key().as_ref(), buyer.key().as_ref()],
+        bump
+    )]
+    pub buyer_position: Account<'info, BuyerPosition>,
+
+    #[account(
+        seeds = [b"seed", ico_account.key().as_ref(), buyer.key().as_ref()],
+        bump = seed_allocation.bump
+    )]
+    pub seed_allocation: Option<Account<'info, SeedAllocation>>,
+
+    #[account(address = ico_account.token_vault @ IcoError::InvalidTokenAccount)]
+    pub token_vault: Account<'info, TokenAccount>,
+
+    pub payment_mint: Account<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = buyer_payment_account.owner == buyer.key() @ IcoError::InvalidTokenAccount,
+        constraint = buyer_payment_account.mint == payment_mint.key() @ IcoError::InvalidPaymentMint
+    )]
+    pub buyer_payment_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"payment_vault", ico_account.key().as_ref(), payment_mint.key().as_ref()],
+        bump
+    )]
+    pub payment_vault: Account<'info, TokenAccount>,
+
+    /// CHECK: Instructions sysvar, read to find the KYC attestation
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, token::Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AddPaymentMint<'info> {
+    #[account(mut)]
+    pub treasurer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"ico", ico_account.token_mint.as_ref()],
+        bump,
+        constraint = ico_account.has_role(Role::Treasurer, &treasurer.key()) @ IcoError::Unauthorized
+    )]
+    pub ico_account: Account<'info, IcoAccount>,
+
+    pub payment_mint: Account<'info, Mint>,
+
+    #[account(
+        init,
+        payer = treasurer,
+        token::mint = payment_mint,
+        token::authority = ico_account,
+        seeds = [b"payment_vault", ico_account.key().as_ref(), payment_mint.key().as_ref()],
+        bump
+    )]
+    pub payment_vault: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, token::Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct DistributeTokens<'info> {
+    #[account(mut)]
+    pub distributor: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"ico", ico_account.token_mint.as_ref()],
+        bump,
+        constraint = ico_account.has_role(Role::Distributor, &distributor.key()) @ IcoError::Unauthorized,
+        constraint = !ico_account.is_active @ IcoError::IcoStillActive,
+        constraint = !ico_account.is_refunding @ IcoError::RefundsInProgress,
+        constraint = !ico_account.is_paused @ IcoError::SalePaused
+    )]
+    pub ico_account: Account<'info, IcoAccount>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"purchase",
+            ico_account.key().as_ref(),
+            purchase_account.buyer.as_ref(),
+            &purchase_account.index.to_le_bytes()
+        ],
+        bump = purchase_account.bump,
+        constraint = !purchase_account.is_distributed @ IcoError::AlreadyDistributed,
+        constraint = purchase_account.ico == ico_account.key() @ IcoError::InvalidPurchase
+    )]
+    pub purchase_account: Account<'info, PurchaseAccount>,
+
+    #[account(mut, address = ico_account.token_vault @ IcoError::InvalidTokenAccount)]
+    pub token_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = buyer_token_account.owner == purchase_account.buyer @ IcoError::InvalidTokenAccount,
+        constraint = buyer_token_account.mint == ico_account.token_mint @ IcoError::InvalidTokenMint
+    )]
+    pub buyer_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, token::Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct BatchDistributeTokens<'info> {
+    pub distributor: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"ico", ico_account.token_mint.as_ref()],
+        bump,
+        constraint = ico_account.has_role(Role::Distributor, &distributor.key()) @ IcoError::Unauthorized,
+        constraint = !ico_account.is_active @ IcoError::IcoStillActive,
+        constraint = !ico_account.is_refunding @ IcoError::RefundsInProgress,
+        constraint = !ico_account.is_paused @ IcoError::SalePaused
+    )]
+    pub ico_account: Account<'info, IcoAccount>,
 
-#[account]
-pub struct PurchaseAccount {
-    pub buyer: Pubkey,        // 32
-    pub amount: u64,          // 8
-    pub is_distributed: bool, // 1
-    pub timestamp: i64,       // 8
-    pub ico: Pubkey,          // 32
-    pub purchase_price: u64,  // 8
-    pub round: RoundType,     // 1
+    #[account(mut, address = ico_account.token_vault @ IcoError::InvalidTokenAccount)]
+    pub token_vault: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, token::Token>,
 }
 
 #[derive(Accounts)]
-pub struct UpdateRound<'info> {
+pub struct ClaimTokens<'info> {
     #[account(mut)]
-    pub authority: Signer<'info>,
+    pub buyer: Signer<'info>,
 
-    #[account(mut, seeds = [b"ico"], bump)]
+    #[account(
+        mut,
+        seeds = [b"ico", ico_account.token_mint.as_ref()],
+        bump,
+        constraint = !ico_account.is_active @ IcoError::IcoStillActive,
+        constraint = !ico_account.is_refunding @ IcoError::RefundsInProgress,
+        constraint = !ico_account.is_paused @ IcoError::SalePaused
+    )]
     pub ico_account: Account<'info, IcoAccount>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"purchase",
+            ico_account.key().as_ref(),
+            purchase_account.buyer.as_ref(),
+            &purchase_account.index.to_le_bytes()
+        ],
+        bump = purchase_account.bump,
+        constraint = purchase_account.buyer == buyer.key() @ IcoError::Unauthorized,
+        constraint = !purchase_account.is_distributed @ IcoError::AlreadyDistributed,
+        constraint = purchase_account.ico == ico_account.key() @ IcoError::InvalidPurchase
+    )]
+    pub purchase_account: Account<'info, PurchaseAccount>,
+
+    #[account(mut, address = ico_account.token_vault @ IcoError::InvalidTokenAccount)]
+    pub token_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = buyer_token_account.owner == buyer.key() @ IcoError::InvalidTokenAccount,
+        constraint = buyer_token_account.mint == ico_account.token_mint @ IcoError::InvalidTokenMint
+    )]
+    pub buyer_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, token::Token>,
 }
 
 #[derive(Accounts)]
-pub struct Initialize<'info> {
+pub struct ClosePurchase<'info> {
     #[account(mut)]
-    pub authority: Signer<'info>,
+    pub buyer: Signer<'info>,
+
+    #[account(seeds = [b"ico", ico_account.token_mint.as_ref()], bump)]
+    pub ico_account: Account<'info, IcoAccount>,
 
     #[account(
-        init,
-        payer = authority,
-        space = 8 +    // discriminator
-            32 +       // authority
-            32 +       // token_mint
-            8 +        // total_supply
-            8 +        // seed_price
-            8 +        // pre_ico_price
-            8 +        // public_price
-            8 +        // current_public_price
-            8 +        // tokens_sold
-            8 +        // seed_tokens_sold
-            8 +        // public_tokens_sold
-            8 +        // start_time
-            8 +        // duration
-            1 +        // is_active
-            1 +        // round_type
-            4 + (32 * 100) + // seed_investors vector with max 100 investors
-            8 +        // total_investors
-            8 +        // purchase_counter
-            8 +        // seed_round_allocation
-            8 +        // pre_ico_allocation
-            8,         // public_round_allocation
-        seeds = [b"ico"],
-        bump
+        mut,
+        close = buyer,
+        seeds = [
+            b"purchase",
+            ico_account.key().as_ref(),
+            purchase_account.buyer.as_ref(),
+            &purchase_account.index.to_le_bytes()
+        ],
+        bump = purchase_account.bump,
+        constraint = purchase_account.buyer == buyer.key() @ IcoError::Unauthorized,
+        constraint = purchase_account.is_distributed @ IcoError::PurchaseNotSettled
     )]
+    pub purchase_account: Account<'info, PurchaseAccount>,
+}
+
+#[derive(Accounts)]
+pub struct EndIco<'info> {
+    #[account(mut, seeds = [b"ico", ico_account.token_mint.as_ref()], bump, has_one = treasury_wallet)]
     pub ico_account: Account<'info, IcoAccount>,
 
+    #[account(mut, seeds = [b"escrow", ico_account.key().as_ref()], bump)]
+    pub escrow: SystemAccount<'info>,
+
     #[account(mut)]
-    pub token_mint: Account<'info, Mint>,
+    /// CHECK: Checked against the treasury stored on the ICO account
+    pub treasury_wallet: UncheckedAccount<'info>,
+
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct BuyTokens<'info> {
+pub struct Refund<'info> {
     #[account(mut)]
     pub buyer: Signer<'info>,
 
-    #[account(mut, seeds = [b"ico"], bump)]
+    #[account(
+        mut,
+        seeds = [b"ico", ico_account.token_mint.as_ref()],
+        bump,
+        constraint = ico_account.is_refunding @ IcoError::RefundsNotAvailable
+    )]
     pub ico_account: Account<'info, IcoAccount>,
 
     #[account(
-        init,
-        payer = buyer,
-        space = 8 + 32 + 8 + 1 + 8 + 32 + 8 + 1, // discriminator + fields
-        seeds = [b"purchase", buyer.key().as_ref(), &ico_account.purchase_counter.to_le_bytes()],
-        bump
+        mut,
+        close = buyer,
+        seeds = [
+            b"purchase",
+            ico_account.key().as_ref(),
+            purchase_account.buyer.as_ref(),
+            &purchase_account.index.to_le_bytes()
+        ],
+        bump = purchase_account.bump,
+        constraint = purchase_account.buyer == buyer.key() @ IcoError::Unauthorized,
+        constraint = purchase_account.ico == ico_account.key() @ IcoError::InvalidPurchase,
+        constraint = purchase_account.claimed_amount == 0 @ IcoError::AlreadyClaimed,
+        constraint = purchase_account.payment_mint == Pubkey::default() @ IcoError::InvalidPaymentMint
     )]
     pub purchase_account: Account<'info, PurchaseAccount>,
 
-    #[account(mut)]
-    /// CHECK: This is safe because we only transfer SOL to this account
-    pub treasury_wallet: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"escrow", ico_account.key().as_ref()], bump)]
+    pub escrow: SystemAccount<'info>,
 
-    pub token_program: Program<'info, token::Token>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct DistributeTokens<'info> {
+pub struct RefundSpl<'info> {
     #[account(mut)]
-    pub authority: Signer<'info>,
+    pub buyer: Signer<'info>,
 
     #[account(
         mut,
-        seeds = [b"ico"],
+        seeds = [b"ico", ico_account.token_mint.as_ref()],
         bump,
-        has_one = authority
+        constraint = ico_account.is_refunding @ IcoError::RefundsNotAvailable
     )]
     pub ico_account: Account<'info, IcoAccount>,
 
     #[account(
         mut,
-        seeds = [b"purchase", purchase_account.buyer.as_ref(), &[]], // Added empty slice as additional seed
-        bump,
-        constraint = !purchase_account.is_distributed @ IcoError::AlreadyDistributed,
-        constraint = purchase_account.ico == ico_account.key() @ IcoError::InvalidPurchase
+        close = buyer,
+        seeds = [
+            b"purchase",
+            ico_account.key().as_ref(),
+            purchase_account.buyer.as_ref(),
+            &purchase_account.index.to_le_bytes()
+        ],
+        bump = purchase_account.bump,
+        constraint = purchase_account.buyer == buyer.key() @ IcoError::Unauthorized,
+        constraint = purchase_account.ico == ico_account.key() @ IcoError::InvalidPurchase,
+        constraint = purchase_account.claimed_amount == 0 @ IcoError::AlreadyClaimed
     )]
     pub purchase_account: Account<'info, PurchaseAccount>,
 
     #[account(
         mut,
-        constraint = treasury_token_account.owner == authority.key() @ IcoError::Unauthorized,
-        constraint = treasury_token_account.mint == ico_account.token_mint @ IcoError::InvalidTokenMint
+        seeds = [b"payment_vault", ico_account.key().as_ref(), purchase_account.payment_mint.as_ref()],
+        bump
     )]
-    pub treasury_token_account: Account<'info, TokenAccount>,
+    pub payment_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = buyer_payment_account.owner == buyer.key() @ IcoError::InvalidTokenAccount,
+        constraint = buyer_payment_account.mint == purchase_account.payment_mint @ IcoError::InvalidPaymentMint
+    )]
+    pub buyer_payment_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, token::Token>,
+}
+
+#[derive(Accounts)]
+pub struct SettleRound<'info> {
+    #[account(
+        mut,
+        seeds = [b"ico", ico_account.token_mint.as_ref()],
+        bump,
+        has_one = token_mint,
+        has_one = token_vault
+    )]
+    pub ico_account: Account<'info, IcoAccount>,
 
     #[account(mut)]
-    /// CHECK: Account checked in token transfer
-    pub buyer_token_account: UncheckedAccount<'info>,
+    pub token_mint: Account<'info, Mint>,
+
+    #[account(mut)]
+    pub token_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = reserve_token_account.owner == ico_account.treasury_wallet @ IcoError::InvalidTokenAccount,
+        constraint = reserve_token_account.mint == ico_account.token_mint @ IcoError::InvalidTokenMint
+    )]
+    pub reserve_token_account: Option<Account<'info, TokenAccount>>,
 
     pub token_program: Program<'info, token::Token>,
-    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct EndIco<'info> {
-    #[account(mut, seeds = [b"ico"], bump)]
+pub struct WithdrawPaymentTokens<'info> {
+    #[account(
+        seeds = [b"ico", ico_account.token_mint.as_ref()],
+        bump,
+        constraint = ico_account.proceeds_withdrawable() @ IcoError::IcoStillActive
+    )]
     pub ico_account: Account<'info, IcoAccount>,
+
+    #[account(
+        mut,
+        seeds = [b"payment_vault", ico_account.key().as_ref(), payment_vault.mint.as_ref()],
+        bump
+    )]
+    pub payment_vault: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = treasury_token_account.owner == ico_account.treasury_wallet @ IcoError::InvalidTokenAccount,
+        constraint = treasury_token_account.mint == payment_vault.mint @ IcoError::InvalidPaymentMint
+    )]
+    pub treasury_token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, token::Token>,
 }
 #[derive(Accounts)]
+#[instruction(investor: Pubkey)]
 pub struct AddSeedInvestor<'info> {
     #[account(mut)]
-    pub authority: Signer<'info>,
-    #[account(mut, seeds = [b"ico"], bump)]
+    pub whitelist_manager: Signer<'info>,
+    #[account(seeds = [b"ico", ico_account.token_mint.as_ref()], bump)]
+    pub ico_account: Account<'info, IcoAccount>,
+
+    #[account(
+        init,
+        payer = whitelist_manager,
+        space = 8 + 32 + 32 + 8 + 8 + 1, // discriminator + fields
+        seeds = [b"seed", ico_account.key().as_ref(), investor.as_ref()],
+        bump
+    )]
+    pub seed_allocation: Account<'info, SeedAllocation>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ConfigureSeedWhitelist<'info> {
+    pub whitelist_manager: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"ico", ico_account.token_mint.as_ref()],
+        bump,
+        constraint = ico_account.has_role(Role::WhitelistManager, &whitelist_manager.key()) @ IcoError::Unauthorized
+    )]
     pub ico_account: Account<'info, IcoAccount>,
 }
 
 #[derive(Accounts)]
 pub struct RemoveSeedInvestor<'info> {
     #[account(mut)]
-    pub authority: Signer<'info>,
-    #[account(mut, seeds = [b"ico"], bump)]
+    pub whitelist_manager: Signer<'info>,
+    #[account(seeds = [b"ico", ico_account.token_mint.as_ref()], bump)]
     pub ico_account: Account<'info, IcoAccount>,
+
+    #[account(
+        mut,
+        close = whitelist_manager,
+        seeds = [b"seed", ico_account.key().as_ref(), seed_allocation.investor.as_ref()],
+        bump = seed_allocation.bump
+    )]
+    pub seed_allocation: Account<'info, SeedAllocation>,
 }
 
 #[error_code]
 pub enum IcoError {
     #[msg("ICO is not currently active")]
     IcoNotActive,
-    #[msg("Insufficient tokens remaining")]
+    #[msg("Purchase exceeds the tokens remaining under the sale hard cap")]
     InsufficientTokens,
     #[msg("ICO is still active")]
     IcoStillActive,
@@ -470,6 +4077,114 @@ pub enum IcoError {
     RoundAllocationExceeded,
     #[msg("No more rounds available")]
     NoMoreRounds,
+    #[msg("Invalid token account")]
+    InvalidTokenAccount,
+    #[msg("Sale token vault cannot cover this purchase")]
+    InsufficientVaultBalance,
+    #[msg("Invalid vesting schedule")]
+    InvalidVestingSchedule,
+    #[msg("Purchased tokens are still vesting")]
+    TokensStillVesting,
+    #[msg("No vested tokens available to claim")]
+    NothingToClaim,
+    #[msg("Refunds are only available after a sale misses its soft cap")]
+    RefundsNotAvailable,
+    #[msg("The sale missed its soft cap and is refunding buyers")]
+    RefundsInProgress,
+    #[msg("Minimum purchase must not exceed the per-wallet maximum")]
+    InvalidPurchaseLimits,
+    #[msg("Purchase is below the round minimum")]
+    BelowMinimumPurchase,
+    #[msg("Purchase exceeds the per-wallet limit for this round")]
+    WalletLimitExceeded,
+    #[msg("Payment mint is not accepted for this round")]
+    PaymentMintNotAccepted,
+    #[msg("Payment mint is already accepted")]
+    PaymentMintAlreadyExists,
+    #[msg("Maximum number of payment mints reached")]
+    TooManyPaymentMints,
+    #[msg("Invalid payment mint")]
+    InvalidPaymentMint,
+    #[msg("Invalid price oracle account")]
+    InvalidOracle,
+    #[msg("Invalid price oracle configuration")]
+    InvalidOracleConfig,
+    #[msg("Oracle price is not currently available")]
+    OraclePriceUnavailable,
+    #[msg("Oracle price is stale")]
+    StaleOraclePrice,
+    #[msg("Oracle price confidence interval is too wide")]
+    OracleConfidenceTooWide,
+    #[msg("Purchase has not been fully distributed yet")]
+    PurchaseNotSettled,
+    #[msg("Remaining accounts must be (purchase, buyer token account) pairs")]
+    InvalidRemainingAccounts,
+    #[msg("Value cannot be lowered below the amount already sold")]
+    BelowAmountSold,
+    #[msg("Price must be greater than zero")]
+    InvalidPrice,
+    #[msg("Start time cannot change once the sale has opened")]
+    SaleAlreadyStarted,
+    #[msg("Invalid sale start time or duration")]
+    InvalidSaleWindow,
+    #[msg("Round allocations exceed the total supply")]
+    AllocationExceedsSupply,
+    #[msg("The sale is paused")]
+    SalePaused,
+    #[msg("The sale is not paused")]
+    SaleNotPaused,
+    #[msg("No matching pending authority transfer")]
+    InvalidPendingAuthority,
+    #[msg("Roles cannot be granted to the default public key")]
+    InvalidRoleHolder,
+    #[msg("This change must go through a council proposal")]
+    CouncilApprovalRequired,
+    #[msg("Council needs 1 to 10 unique members and a threshold no larger than its size")]
+    InvalidCouncilConfig,
+    #[msg("Signer is not a council member")]
+    NotCouncilMember,
+    #[msg("Member has already approved this proposal")]
+    ProposalAlreadyApproved,
+    #[msg("Proposal has already been executed")]
+    ProposalAlreadyExecuted,
+    #[msg("Proposal has not reached the approval threshold")]
+    ThresholdNotReached,
+    #[msg("Proposal timelock has not elapsed")]
+    TimelockNotElapsed,
+    #[msg("Invalid treasury wallet")]
+    InvalidTreasuryWallet,
+    #[msg("Seed whitelist proof does not match the published merkle root")]
+    InvalidMerkleProof,
+    #[msg("Purchase exceeds the investor's seed allocation")]
+    InvestorAllocationExceeded,
+    #[msg("Seed allocation must be non-zero and within the seed round allocation")]
+    InvalidSeedAllocation,
+    #[msg("Purchase requires a KYC attestation verified in the preceding instruction")]
+    MissingKycAttestation,
+    #[msg("KYC attestation is malformed or not signed by the KYC signer")]
+    InvalidKycAttestation,
+    #[msg("KYC attestation has expired")]
+    KycAttestationExpired,
+    #[msg("Rounds must be time-ordered, non-overlapping and scheduled at most once")]
+    InvalidRoundSchedule,
+    #[msg("Round is not part of the sale schedule")]
+    RoundNotScheduled,
+    #[msg("No sale round is open at this time")]
+    NoActiveRound,
+    #[msg("Seed round purchases require a seed allocation or whitelist proof")]
+    SeedDealRequired,
+    #[msg("Round has not ended yet")]
+    RoundStillActive,
+    #[msg("Round has already been settled")]
+    RoundAlreadySettled,
+    #[msg("Earlier rounds must be settled first")]
+    EarlierRoundNotSettled,
+    #[msg("Tokens have already been claimed from this purchase")]
+    AlreadyClaimed,
+    #[msg("Purchase amount must be greater than zero")]
+    ZeroPurchaseAmount,
+    #[msg("Proposal action needs an account that was not provided")]
+    MissingProposalAccount,
 }
 
 impl Default for RoundType {
@@ -478,6 +4193,559 @@ impl Default for RoundType {
     }
 }
 
+impl Default for VestingSchedule {
+    fn default() -> Self {
+        VestingSchedule {
+            tge_unlock_bps: 10000,
+            cliff_duration: 0,
+            vesting_duration: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mock_price_account(price: i64, conf: u64, expo: i32, publish_time: i64) -> Vec<u8> {
+        let mut data = vec![0u8; 240];
+        data[0..4].copy_from_slice(&PYTH_MAGIC.to_le_bytes());
+        data[8..12].copy_from_slice(&PYTH_PRICE_ACCOUNT.to_le_bytes());
+        data[20..24].copy_from_slice(&expo.to_le_bytes());
+        data[96..104].copy_from_slice(&publish_time.to_le_bytes());
+        data[208..216].copy_from_slice(&price.to_le_bytes());
+        data[216..224].copy_from_slice(&conf.to_le_bytes());
+        data[224..228].copy_from_slice(&PYTH_STATUS_TRADING.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn oracle_price_loads_mock_account() {
+        let data = mock_price_account(150_00000000, 5_000_000, -8, 1_000);
+        let price = OraclePrice::load(&data).unwrap();
+
+        assert_eq!(price.price, 150_00000000);
+        assert_eq!(price.conf, 5_000_000);
+        assert_eq!(price.expo, -8);
+        assert_eq!(price.publish_time, 1_000);
+    }
+
+    #[test]
+    fn oracle_price_rejects_foreign_or_halted_accounts() {
+        let mut data = mock_price_account(150_00000000, 0, -8, 1_000);
+        data[0] ^= 0xff;
+        assert_eq!(
+            OraclePrice::load(&data).err().unwrap(),
+            IcoError::InvalidOracle.into()
+        );
+
+        let mut data = mock_price_account(150_00000000, 0, -8, 1_000);
+        data[224..228].copy_from_slice(&0u32.to_le_bytes());
+        assert_eq!(
+            OraclePrice::load(&data).err().unwrap(),
+            IcoError::OraclePriceUnavailable.into()
+        );
+
+        assert_eq!(
+            OraclePrice::load(&data[..200]).err().unwrap(),
+            IcoError::InvalidOracle.into()
+        );
+    }
+
+    #[test]
+    fn oracle_price_rejects_stale_price() {
+        let price = OraclePrice::load(&mock_price_account(150_00000000, 0, -8, 1_000)).unwrap();
+
+        assert!(price.validate(1_060, 60, 100).is_ok());
+        assert_eq!(
+            price.validate(1_061, 60, 100).err().unwrap(),
+            IcoError::StaleOraclePrice.into()
+        );
+    }
+
+    #[test]
+    fn oracle_price_rejects_wide_confidence() {
+        // 1% confidence interval around $150
+        let price =
+            OraclePrice::load(&mock_price_account(150_00000000, 1_50000000, -8, 1_000)).unwrap();
+
+        assert!(price.validate(1_000, 60, 100).is_ok());
+        assert_eq!(
+            price.validate(1_000, 60, 99).err().unwrap(),
+            IcoError::OracleConfidenceTooWide.into()
+        );
+    }
+
+    #[test]
+    fn lamports_for_handles_both_exponent_signs() {
+        // $150 per SOL, one whole 9-decimal token at $0.15 costs 0.001 SOL
+        let negative = OraclePrice::load(&mock_price_account(150_00000000, 0, -8, 0)).unwrap();
+        let positive = OraclePrice::load(&mock_price_account(15, 0, 1, 0)).unwrap();
+
+        assert_eq!(
+            negative.lamports_for(1_000_000_000, 150_000, 9).unwrap(),
+            1_000_000
+        );
+        assert_eq!(
+            positive.lamports_for(1_000_000_000, 150_000, 9).unwrap(),
+            1_000_000
+        );
+    }
+
+    #[test]
+    fn lamports_for_rounds_up() {
+        let price = OraclePrice::load(&mock_price_account(150_00000000, 0, -8, 0)).unwrap();
+
+        // A single base unit is worth a thousandth of a lamport
+        assert_eq!(price.lamports_for(1, 150_000, 9).unwrap(), 1);
+        assert_eq!(
+            price.lamports_for(1_000_000_001, 150_000, 9).unwrap(),
+            1_000_001
+        );
+    }
+
+    #[test]
+    fn spl_cost_is_priced_per_whole_token() {
+        // 0.15 USDC (6 decimals) per whole 9-decimal token
+        assert_eq!(
+            PaymentMint::cost_for(2_000_000_000, 150_000, 9).unwrap(),
+            300_000
+        );
+        assert_eq!(PaymentMint::cost_for(1, 150_000, 9).unwrap(), 1);
+        assert_eq!(PaymentMint::cost_for(7, 3, 0).unwrap(), 21);
+    }
+
+    fn mock_round(
+        round: RoundType,
+        start_time: i64,
+        end_time: i64,
+        allocation: u64,
+        tokens_sold: u64,
+    ) -> RoundSchedule {
+        RoundSchedule {
+            round,
+            start_time,
+            end_time,
+            price: 10,
+            allocation,
+            tokens_sold,
+            lamports_raised: 0,
+            buyers: 0,
+            rollover: RolloverPolicy::CarryForward,
+            is_settled: false,
+        }
+    }
+    #[test]
+    fn vault_keeps_unpaid_purchases_and_unsold_allocations() {
+        let ico = IcoAccount {
+            rounds: vec![
+                mock_round(RoundType::Seed, 0, 100, 300, 300),
+                mock_round(RoundType::PublicICO, 100, 200, 700, 200),
+            ],
+            tokens_sold: 500,
+            tokens_distributed: 150,
+            ..Default::default()
+        };
+
+        // 350 owed to buyers plus 500 still on offer in the public round
+        assert_eq!(ico.reserved_sale_tokens().unwrap(), 850);
+    }
+
+    #[test]
+    fn refunds_release_their_tokens_from_the_vault() {
+        let mut ico = IcoAccount {
+            rounds: vec![
+                mock_round(RoundType::Seed, 0, 100, 300, 300),
+                mock_round(RoundType::PublicICO, 100, 200, 700, 200),
+            ],
+            tokens_sold: 500,
+            is_refunding: true,
+            ..Default::default()
+        };
+        assert_eq!(ico.reserved_sale_tokens().unwrap(), 500);
+
+        let purchase = PurchaseAccount {
+            amount: 200,
+            round: RoundType::PublicICO,
+            ..Default::default()
+        };
+        ico.release_refunded(&purchase).unwrap();
+
+        assert_eq!(ico.tokens_sold, 300);
+        assert_eq!(
+            ico.round_schedule(RoundType::PublicICO)
+                .unwrap()
+                .tokens_sold,
+            0
+        );
+        assert_eq!(ico.reserved_sale_tokens().unwrap(), 300);
+    }
+
+    fn vesting_purchase(
+        tge_unlock_bps: u16,
+        cliff_duration: i64,
+        vesting_duration: i64,
+    ) -> PurchaseAccount {
+        PurchaseAccount {
+            amount: 10_000,
+            vesting: VestingSchedule {
+                tge_unlock_bps,
+                cliff_duration,
+                vesting_duration,
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn vesting_unlocks_tge_share_then_linearly_after_the_cliff() {
+        // 10% at TGE, 100s cliff, then 1000s linear from a sale ending at 1000
+        let purchase = vesting_purchase(1_000, 100, 1_000);
+
+        assert_eq!(purchase.vested_amount(1_000, 1_000).unwrap(), 1_000);
+        assert_eq!(purchase.vested_amount(1_000, 1_099).unwrap(), 1_000);
+        assert_eq!(purchase.vested_amount(1_000, 1_100).unwrap(), 1_000);
+        assert_eq!(purchase.vested_amount(1_000, 1_600).unwrap(), 5_500);
+        assert_eq!(purchase.vested_amount(1_000, 2_100).unwrap(), 10_000);
+        assert_eq!(purchase.vested_amount(1_000, 9_999).unwrap(), 10_000);
+    }
+
+    #[test]
+    fn vesting_follows_the_sale_end_passed_at_claim_time() {
+        let purchase = vesting_purchase(0, 0, 1_000);
+
+        assert_eq!(purchase.vested_amount(1_000, 1_500).unwrap(), 5_000);
+        // The sale was extended after purchase, so nothing has unlocked yet
+        assert_eq!(purchase.vested_amount(2_000, 1_500).unwrap(), 0);
+    }
+
+    #[test]
+    fn vesting_without_linear_period_unlocks_everything_at_the_cliff() {
+        let purchase = vesting_purchase(2_500, 100, 0);
+
+        assert_eq!(purchase.vested_amount(1_000, 1_099).unwrap(), 2_500);
+        assert_eq!(purchase.vested_amount(1_000, 1_100).unwrap(), 10_000);
+
+        let unlocked = vesting_purchase(0, 0, 0);
+        assert_eq!(unlocked.vested_amount(1_000, 1_000).unwrap(), 10_000);
+    }
+
+    #[test]
+    fn timelock_restarts_when_a_proposal_regains_its_threshold() {
+        let (a, b, c) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let mut council = Council {
+            ico: Pubkey::default(),
+            members: vec![a, b, c],
+            threshold: 2,
+            timelock: 100,
+            proposal_count: 1,
+            bump: 255,
+        };
+        let mut proposal = Proposal {
+            council: Pubkey::default(),
+            index: 0,
+            proposer: a,
+            action: ProposalAction::RevokeRole(Role::Pauser),
+            approvals: vec![a],
+            created_at: 0,
+            approved_at: 0,
+            executed: false,
+            bump: 255,
+        };
+
+        assert_eq!(proposal.add_approval(&council, b, 10), 2);
+        assert_eq!(proposal.approved_at, 10);
+
+        // Removing b drops the proposal below the threshold; c brings it back
+        council.members = vec![a, c];
+        assert_eq!(proposal.add_approval(&council, c, 500), 2);
+        assert_eq!(proposal.approved_at, 500);
+    }
+
+    #[test]
+    fn only_the_purchase_reaching_the_cap_sells_out() {
+        let mut ico = IcoAccount {
+            total_supply: 1_000,
+            rounds: vec![mock_round(RoundType::PublicICO, 0, 100, 1_000, 0)],
+            tokens_sold: 900,
+            ..Default::default()
+        };
+        assert!(!ico.sold_out_by(100).unwrap());
+
+        ico.tokens_sold = 1_000;
+        assert!(ico.sold_out_by(100).unwrap());
+        assert!(!ico.sold_out_by(0).unwrap());
+    }
+
+    #[test]
+    fn unsettled_rounds_carry_forward_without_a_crank() {
+        let ico_key = Pubkey::new_unique();
+        let buyer = Pubkey::new_unique();
+        let mut ico = IcoAccount {
+            is_active: true,
+            start_time: 0,
+            duration: 200,
+            total_supply: 1_000,
+            rounds: vec![
+                mock_round(RoundType::Seed, 0, 100, 300, 100),
+                mock_round(RoundType::PublicICO, 100, 200, 700, 700),
+            ],
+            tokens_sold: 800,
+            ..Default::default()
+        };
+        let mut position = BuyerPosition {
+            buyer: Pubkey::default(),
+            ico: Pubkey::default(),
+            seed_amount: 0,
+            private_amount: 0,
+            pre_ico_amount: 0,
+            public_amount: 0,
+            lamports_paid: 0,
+            purchase_count: 0,
+            bump: 0,
+        };
+        assert_eq!(ico.carried_into(RoundType::PublicICO, 150).unwrap(), 200);
+
+        // The public round sold out its own allocation; the seed round's 200 are still on offer
+        let terms = validate_purchase(
+            &ico,
+            &mut position,
+            ico_key,
+            buyer,
+            255,
+            1_000,
+            200,
+            None,
+            None,
+            None,
+            150,
+        );
+        assert!(terms.is_ok());
+
+        ico.rounds[0].rollover = RolloverPolicy::ReturnToReserve;
+        assert_eq!(ico.carried_into(RoundType::PublicICO, 150).unwrap(), 0);
+    }
+
+    #[test]
+    fn zero_amount_purchases_are_rejected() {
+        let ico_key = Pubkey::new_unique();
+        let buyer = Pubkey::new_unique();
+        let ico = IcoAccount {
+            is_active: true,
+            start_time: 0,
+            duration: 100,
+            total_supply: 1_000,
+            rounds: vec![mock_round(RoundType::PublicICO, 0, 100, 1_000, 0)],
+            ..Default::default()
+        };
+        let mut position = BuyerPosition {
+            buyer: Pubkey::default(),
+            ico: Pubkey::default(),
+            seed_amount: 0,
+            private_amount: 0,
+            pre_ico_amount: 0,
+            public_amount: 0,
+            lamports_paid: 0,
+            purchase_count: 0,
+            bump: 0,
+        };
+
+        let result = validate_purchase(
+            &ico,
+            &mut position,
+            ico_key,
+            buyer,
+            255,
+            1_000,
+            0,
+            None,
+            None,
+            None,
+            50,
+        );
+        assert_eq!(result.err().unwrap(), IcoError::ZeroPurchaseAmount.into());
+    }
+
+    #[test]
+    fn re_added_seed_deal_keeps_counting_earlier_buys() {
+        let ico_key = Pubkey::new_unique();
+        let buyer = Pubkey::new_unique();
+        let ico = IcoAccount {
+            is_active: true,
+            start_time: 0,
+            duration: 100,
+            total_supply: 1_000,
+            rounds: vec![mock_round(RoundType::Seed, 0, 100, 1_000, 100)],
+            tokens_sold: 100,
+            ..Default::default()
+        };
+        // The buyer used up a 100 token deal before it was removed
+        let mut position = BuyerPosition {
+            buyer,
+            ico: ico_key,
+            seed_amount: 100,
+            private_amount: 0,
+            pre_ico_amount: 0,
+            public_amount: 0,
+            lamports_paid: 0,
+            purchase_count: 1,
+            bump: 255,
+        };
+        // Re-adding the same deal starts a fresh SeedAllocation account
+        let seed_allocation = SeedAllocation {
+            ico: ico_key,
+            investor: buyer,
+            allocation: 100,
+            price: 5,
+            bump: 255,
+        };
+
+        let result = validate_purchase(
+            &ico,
+            &mut position,
+            ico_key,
+            buyer,
+            255,
+            1_000,
+            1,
+            None,
+            Some(&seed_allocation),
+            None,
+            50,
+        );
+        assert_eq!(
+            result.err().unwrap(),
+            IcoError::InvestorAllocationExceeded.into()
+        );
+    }
+
+    #[test]
+    fn payment_mint_purchases_count_towards_the_soft_cap() {
+        let mut ico = IcoAccount {
+            is_active: true,
+            token_decimals: 9,
+            total_supply: 1_000_000_000_000,
+            rounds: vec![mock_round(
+                RoundType::PublicICO,
+                0,
+                100,
+                1_000_000_000_000,
+                0,
+            )],
+            // $100 soft cap
+            soft_cap: 100_000_000,
+            ..Default::default()
+        };
+        let mut position = BuyerPosition {
+            buyer: Pubkey::default(),
+            ico: Pubkey::default(),
+            seed_amount: 0,
+            private_amount: 0,
+            pre_ico_amount: 0,
+            public_amount: 0,
+            lamports_paid: 0,
+            purchase_count: 0,
+            bump: 0,
+        };
+        let terms = PurchaseTerms {
+            is_seed_round: false,
+            round: RoundType::PublicICO,
+            round_price: 250_000,
+            vesting: VestingSchedule::default(),
+            is_new_investor: true,
+            is_new_round_buyer: true,
+            seed_price_numerator: 1,
+            seed_price_denominator: 1,
+        };
+
+        // 400 whole tokens at $0.25, paid entirely in a stablecoin
+        record_purchase(
+            &mut ico,
+            &mut position,
+            &mut PurchaseAccount::default(),
+            255,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            &terms,
+            400_000_000_000,
+            250_000,
+            100_000_000,
+            Pubkey::new_unique(),
+            50,
+        )
+        .unwrap();
+
+        assert_eq!(ico.total_raised, 0);
+        assert_eq!(ico.usd_raised, 100_000_000);
+        assert!(ico.close_sale());
+        assert!(ico.proceeds_withdrawable());
+    }
+
+    #[test]
+    fn duration_update_moves_the_last_round_end() {
+        let mut ico = IcoAccount {
+            is_active: true,
+            start_time: 0,
+            duration: 200,
+            total_supply: 1_000,
+            rounds: vec![
+                mock_round(RoundType::Seed, 0, 100, 300, 0),
+                mock_round(RoundType::PublicICO, 100, 200, 700, 0),
+            ],
+            ..Default::default()
+        };
+        ico.rounds[1].rollover = RolloverPolicy::ReturnToReserve;
+        let params = IcoParametersUpdate {
+            duration: Some(300),
+            ..Default::default()
+        };
+
+        apply_ico_parameters(&mut ico, &params, 150).unwrap();
+        assert_eq!(ico.rounds[1].start_time, 100);
+        assert_eq!(ico.rounds[1].end_time, 300);
+        assert_eq!(ico.duration, 300);
+
+        // The last round cannot be made to end before it starts
+        let params = IcoParametersUpdate {
+            duration: Some(50),
+            ..Default::default()
+        };
+        assert!(apply_ico_parameters(&mut ico, &params, 150).is_err());
+    }
+
+    #[test]
+    fn payment_vaults_stay_locked_for_spl_refunds() {
+        let mut ico = IcoAccount {
+            is_active: true,
+            soft_cap: 1,
+            ..Default::default()
+        };
+        assert!(!ico.proceeds_withdrawable());
+
+        assert!(!ico.close_sale());
+        assert!(ico.is_refunding);
+        assert!(!ico.proceeds_withdrawable());
+    }
+
+    #[test]
+    fn payment_vaults_unlock_once_soft_cap_is_met() {
+        let mut ico = IcoAccount {
+            is_active: true,
+            soft_cap: 100,
+            usd_raised: 100,
+            ..Default::default()
+        };
+
+        assert!(ico.close_sale());
+        assert!(!ico.is_refunding);
+        assert!(ico.proceeds_withdrawable());
+    }
+}
+
 
 
 
@@ -1,63 +1,34 @@
-import React, { useState, useEffect } from 'react';
+import React, { useState } from 'react';
 import { useConnection, useWallet } from '@solana/wallet-adapter-react';
 import { PublicKey } from '@solana/web3.js';
 import { getProgram } from '../utils/anchor-connection';
 import * as anchor from '@project-serum/anchor';
 import { isAdminWallet } from '../utils/admin-check';
+import { ICO_SEED } from '../utils/constants';
 import AdminLayout from '../components/AdminLayout';
 import {Input} from '../components/ui/input';
 import { Button, Card, CardContent, CardDescription, CardHeader, CardTitle } from "../components/ui/cardTitle";
 import { Label } from "../components/ui/label";
 
+const USD_MICRO_UNITS = 1000000;
+
+// Blank fields are sent as null, which leaves the current value in place
+const toUsdMicro = (value) =>
+  value === '' ? null : new anchor.BN(Math.round(parseFloat(value) * USD_MICRO_UNITS));
+
 const UpdateIcoParameters = () => {
   const { connection } = useConnection();
   const wallet = useWallet();
-  const [icoData, setIcoData] = useState(null);
   const [formData, setFormData] = useState({
     seedPrice: '',
     preIcoPrice: '',
     publicPrice: '',
     duration: '',
   });
-  const [loading, setLoading] = useState(true);
   const [updating, setUpdating] = useState(false);
   const [error, setError] = useState('');
   const [success, setSuccess] = useState('');
 
-  useEffect(() => {
-    if (wallet.publicKey && isAdminWallet(wallet.publicKey)) {
-      fetchIcoData();
-    }
-  }, [connection, wallet.publicKey]);
-
-  const fetchIcoData = async () => {
-    if (!wallet.publicKey) return;
-
-    setLoading(true);
-    setError('');
-
-    try {
-      const program = getProgram(connection, wallet);
-      const [icoAccount] = await PublicKey.findProgramAddress(
-        [Buffer.from('ico')],
-        program.programId
-      );
-      const data = await program.account.icoAccount.fetch(icoAccount);
-      setIcoData(data);
-      setFormData({
-        seedPrice: (data.seedPrice.toNumber() / anchor.web3.LAMPORTS_PER_SOL).toString(),
-        preIcoPrice: (data.preIcoPrice.toNumber() / anchor.web3.LAMPORTS_PER_SOL).toString(),
-        publicPrice: (data.publicPrice.toNumber() / anchor.web3.LAMPORTS_PER_SOL).toString(),
-        duration: data.duration.toString(),
-      });
-    } catch (err) {
-      console.error('Error fetching ICO data:', err);
-      setError('Failed to fetch ICO data. Please try again later.');
-    } finally {
-      setLoading(false);
-    }
-  };
-
   const handleInputChange = (e) => {
     const { name, value } = e.target;
     setFormData(prevState => ({
@@ -76,18 +47,27 @@ const UpdateIcoParameters = () => {
 
     try {
       const program = getProgram(connection, wallet);
+      const tokenMint = new PublicKey(process.env.REACT_APP_TOKEN_MINT_ADDRESS);
       const [icoAccount] = await PublicKey.findProgramAddress(
-        [Buffer.from('ico')],
+        [Buffer.from(ICO_SEED), tokenMint.toBuffer()],
         program.programId
       );
 
-      const seedPrice = new anchor.BN(parseFloat(formData.seedPrice) * anchor.web3.LAMPORTS_PER_SOL);
-      const preIcoPrice = new anchor.BN(parseFloat(formData.preIcoPrice) * anchor.web3.LAMPORTS_PER_SOL);
-      const publicPrice = new anchor.BN(parseFloat(formData.publicPrice) * anchor.web3.LAMPORTS_PER_SOL);
-      const duration = new anchor.BN(parseInt(formData.duration));
+      const params = {
+        totalSupply: null,
+        seedPrice: toUsdMicro(formData.seedPrice),
+        preIcoPrice: toUsdMicro(formData.preIcoPrice),
+        publicPrice: toUsdMicro(formData.publicPrice),
+        seedRoundAllocation: null,
+        privatePrice: null,
+        privateAllocation: null,
+        preIcoAllocation: null,
+        publicRoundAllocation: null,
+        duration: formData.duration === '' ? null : new anchor.BN(parseInt(formData.duration)),
+      };
 
       const tx = await program.methods
-        .updateIcoParameters(seedPrice, preIcoPrice, publicPrice, duration)
+        .updateIcoParameters(params)
         .accounts({
           icoAccount,
           authority: wallet.publicKey,
@@ -95,7 +75,7 @@ const UpdateIcoParameters = () => {
         .rpc();
 
       setSuccess(`ICO parameters updated successfully! Transaction ID: ${tx}`);
-      await fetchIcoData();
+      setFormData({ seedPrice: '', preIcoPrice: '', publicPrice: '', duration: '' });
     } catch (err) {
       console.error('Error updating ICO parameters:', err);
       setError('Failed to update ICO parameters. Please try again later.');
@@ -122,73 +102,62 @@ const UpdateIcoParameters = () => {
       <Card>
         <CardHeader>
           <CardTitle>Update ICO Parameters</CardTitle>
-          <CardDescription>Modify the current ICO parameters</CardDescription>
+          <CardDescription>Modify the current ICO parameters. Leave a field blank to keep its current value.</CardDescription>
         </CardHeader>
         <CardContent>
-          {loading ? (
-            <div className="text-center py-12">
-              <div className="animate-spin rounded-full h-12 w-12 border-b-2 border-blue-600 mx-auto"></div>
-              <p className="mt-4 text-gray-600">Loading ICO data...</p>
+          <form onSubmit={handleSubmit} className="space-y-4">
+            <div className="space-y-2">
+              <Label htmlFor="seedPrice">Seed Price (USD):</Label>
+              <Input
+                type="number"
+                id="seedPrice"
+                name="seedPrice"
+                value={formData.seedPrice}
+                onChange={handleInputChange}
+                min="0"
+                step="0.000001"
+              />
             </div>
-          ) : (
-            <form onSubmit={handleSubmit} className="space-y-4">
-              <div className="space-y-2">
-                <Label htmlFor="seedPrice">Seed Price (SOL):</Label>
-                <Input
-                  type="number"
-                  id="seedPrice"
-                  name="seedPrice"
-                  value={formData.seedPrice}
-                  onChange={handleInputChange}
-                  required
-                  min="0"
-                  step="0.000000001"
-                />
-              </div>
-              <div className="space-y-2">
-                <Label htmlFor="preIcoPrice">Pre-ICO Price (SOL):</Label>
-                <Input
-                  type="number"
-                  id="preIcoPrice"
-                  name="preIcoPrice"
-                  value={formData.preIcoPrice}
-                  onChange={handleInputChange}
-                  required
-                  min="0"
-                  step="0.000000001"
-                />
-              </div>
-              <div className="space-y-2">
-                <Label htmlFor="publicPrice">Public Price (SOL):</Label>
-                <Input
-                  type="number"
-                  id="publicPrice"
-                  name="publicPrice"
-                  value={formData.publicPrice}
-                  onChange={handleInputChange}
-                  required
-                  min="0"
-                  step="0.000000001"
-                />
-              </div>
-              <div className="space-y-2">
-                <Label htmlFor="duration">Duration (seconds):</Label>
-                <Input
-                  type="number"
-                  id="duration"
-                  name="duration"
-                  value={formData.duration}
-                  onChange={handleInputChange}
-                  required
-                  min="1"
-                />
-              </div>
-              {/* <Button type="submit" disabled={updating} className="w-full"> */}
-              <Button type="submit" disabled={updating} >
-                {updating ? 'Updating...' : 'Update'}
-              </Button>
-            </form>
-          )}
+            <div className="space-y-2">
+              <Label htmlFor="preIcoPrice">Pre-ICO Price (USD):</Label>
+              <Input
+                type="number"
+                id="preIcoPrice"
+                name="preIcoPrice"
+                value={formData.preIcoPrice}
+                onChange={handleInputChange}
+                min="0"
+                step="0.000001"
+              />
+            </div>
+            <div className="space-y-2">
+              <Label htmlFor="publicPrice">Public Price (USD):</Label>
+              <Input
+                type="number"
+                id="publicPrice"
+                name="publicPrice"
+                value={formData.publicPrice}
+                onChange={handleInputChange}
+                min="0"
+                step="0.000001"
+              />
+            </div>
+            <div className="space-y-2">
+              <Label htmlFor="duration">Sale Duration (seconds from start):</Label>
+              <Input
+                type="number"
+                id="duration"
+                name="duration"
+                value={formData.duration}
+                onChange={handleInputChange}
+                min="1"
+              />
+            </div>
+            {/* <Button type="submit" disabled={updating} className="w-full"> */}
+            <Button type="submit" disabled={updating} >
+              {updating ? 'Updating...' : 'Update'}
+            </Button>
+          </form>
           {error && (
             <div className="mt-4 p-4 bg-red-100 border border-red-400 text-red-700 rounded-md text-sm">
               {error}